@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::float::Float;
+use crate::gelu::{gelu, gelu_backward, gelu_erf, gelu_erf_backward};
+use crate::silu::{silu, silu_backward};
+
+/// The elementwise nonlinearity of `FeedForward`, chosen by `Config::activation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    /// Exact GELU, `x * Phi(x)`, with the standard normal CDF computed from `erf`.
+    Gelu,
+    /// The tanh approximation of GELU used by GPT-2.
+    GeluTanh,
+    /// `x * sigmoid(x)`, also called Swish.
+    #[serde(alias = "swish")]
+    Silu,
+    Relu,
+    /// `relu(x)^2`, from Primer.
+    ReluSquared,
+}
+
+impl Activation {
+    pub fn apply<T: Float>(&self, x: T) -> T {
+        match self {
+            Activation::Gelu => gelu_erf(x),
+            Activation::GeluTanh => gelu(x),
+            Activation::Silu => silu(x),
+            Activation::Relu => x.max(T::zero()),
+            Activation::ReluSquared => {
+                let relu = x.max(T::zero());
+                relu * relu
+            }
+        }
+    }
+
+    /// The derivative of `apply` at `x`. ReLU's is taken as `0` at `0`.
+    pub fn derivative<T: Float>(&self, x: T) -> T {
+        match self {
+            Activation::Gelu => gelu_erf_backward(T::one(), x),
+            Activation::GeluTanh => gelu_backward(T::one(), x),
+            Activation::Silu => silu_backward(T::one(), x),
+            Activation::Relu => {
+                if x > T::zero() { T::one() } else { T::zero() }
+            }
+            Activation::ReluSquared => x.max(T::zero()) * T::from_f64(2.0),
+        }
+    }
+
+    /// Applies the activation to every element of `input`.
+    pub fn forward<T: Float>(&self, input: &Var<T>) -> Var<T> {
+        let activation = *self;
+        input.map(|x| activation.apply(x), move |x| activation.derivative(x))
+    }
+}
@@ -0,0 +1,31 @@
+use crate::float::Float;
+use crate::tensor::Tensor;
+
+/// The per-head ALiBi slopes from Press et al.: a geometric sequence starting at
+/// `2^(-8 / num_heads)` for a power-of-two head count, otherwise those of the next lower power of
+/// two followed by every other slope of twice that count.
+pub fn alibi_slopes(num_heads: usize) -> Vec<f64> {
+    let geometric = |n: usize| (1..=n).map(move |h| 2f64.powf(-8.0 * h as f64 / n as f64));
+    if num_heads.is_power_of_two() {
+        return geometric(num_heads).collect();
+    }
+    let closest = 1 << num_heads.ilog2();
+    geometric(closest)
+        .chain(geometric(2 * closest).step_by(2).take(num_heads - closest))
+        .collect()
+}
+
+/// The `[num_heads, seq_len, past_len + seq_len]` score biases for queries at positions
+/// `past_len..` over keys `0..past_len + seq_len`: `-slope * distance`, so each head prefers
+/// nearby keys at its own rate. The distance is absolute, which also covers bidirectional
+/// attention.
+pub fn alibi_bias<T: Float>(slopes: &[f64], seq_len: usize, past_len: usize) -> Tensor<T> {
+    let key_len = past_len + seq_len;
+    let mut data = Vec::with_capacity(slopes.len() * seq_len * key_len);
+    for &slope in slopes {
+        for i in past_len..key_len {
+            data.extend((0..key_len).map(|j| T::from_f64(-slope * i.abs_diff(j) as f64)));
+        }
+    }
+    Tensor::new(data, &[slopes.len(), seq_len, key_len])
+}
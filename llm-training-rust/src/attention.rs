@@ -1,153 +1,537 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::alibi::{alibi_bias, alibi_slopes};
 use crate::config::Config;
 use crate::linear::Linear;
 use crate::dropout::Dropout;
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::module::{prefixed, Module};
+use crate::parameter::Parameter;
+use crate::positional_encoding::{PositionEncoding, PositionScaling};
+use crate::rotary::RotaryEmbedding;
+use crate::rng;
+use crate::tiled_attention::{tiled_attention, ScoreBias};
+use crate::tensor::Tensor;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+/// Multi-head scaled dot-product attention: self-attention, or cross-attention from one sequence
+/// over another (see `Attention::cross`).
+///
+/// The query, key and value projections hold all heads side by side: head `h` owns columns
+/// `h * head_dim..(h + 1) * head_dim`, where `head_dim = embedding_dim / num_heads`. Each head
+/// gets its own score matrix and softmax, and the head outputs are concatenated before
+/// `output_matrix`. With `causal` set, position `i` only attends to positions `0..=i`.
+///
+/// With fewer key/value heads than query heads (`Config::num_kv_heads`), the key and value
+/// projections only produce `num_kv_heads * head_dim` columns and query head `h` reads key/value
+/// head `h / (num_heads / num_kv_heads)`. The KV cache shrinks by the same factor.
+///
+/// With rotary position embeddings, queries and keys are rotated by their absolute position
+/// before the scores are taken; cached keys are stored already rotated. With ALiBi, each head
+/// instead adds `-slope * distance` to its scores.
+///
+/// With a `window`, position `i` only attends to keys fewer than `window` positions away, and
+/// the KV cache evicts keys that no later query can reach, so decoding memory stays bounded.
+///
+/// With `streaming` set, decoding keeps only a few leading "sink" positions and a rolling window
+/// of recent ones; see `Streaming`.
+///
+/// With `Config::attention_block_size` set, the scores are computed block by block by
+/// `tiled_attention` and never stored, so `attention_weights` stays `None`.
+///
+/// `forward_step` decodes incrementally against a `KvCache` of the keys and values of earlier
+/// positions; the cache is runtime state and is not saved in checkpoints.
+pub struct Attention<T: Float = f32> {
+    num_heads: usize,
+    num_kv_heads: usize,
+    causal: bool,
+    rotary: Option<RotaryEmbedding>,
+    alibi_slopes: Option<Vec<f64>>,
+    block_size: Option<usize>,
+    window: Option<usize>,
+    query_matrix: Linear<T>,
+    key_matrix: Linear<T>,
+    value_matrix: Linear<T>,
+    output_matrix: Linear<T>,
+    dropout: Dropout<T>,
+    /// The `[batch, num_heads, seq, key_len]` attention probabilities of the last forward pass,
+    /// before dropout.
+    #[serde(skip)]
+    pub attention_weights: Option<Tensor<T>>,
+    #[serde(skip)]
+    kv_cache: KvCache<T>,
+    #[serde(skip)]
+    streaming: Option<Streaming>,
+}
+
+/// StreamingLLM-style decoding with attention sinks: the KV cache keeps the first `num_sinks`
+/// positions, which absorb attention mass that has nowhere else to go, plus the `window` most
+/// recent ones, so generation can run indefinitely in constant memory.
+///
+/// Positions are remapped to indices in the cache: the sinks stay at `0..num_sinks` and the
+/// recent keys follow contiguously, however many tokens were evicted between them. Rotary keys
+/// are therefore cached unrotated and rotated by their cache index on every step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Streaming {
+    pub num_sinks: usize,
+    pub window: usize,
+}
+
+/// The keys and values of the positions processed by `Attention::forward_step` since the last
+/// `clear`, each `[batch, num_kv_heads, len, head_dim]`. Windowed and streaming attention evict
+/// positions that are no longer needed.
+#[derive(Debug, Clone)]
+pub struct KvCache<T: Float = f32> {
+    keys: Option<Tensor<T>>,
+    values: Option<Tensor<T>>,
+    evicted: usize,
+}
+
+impl<T: Float> Default for KvCache<T> {
+    fn default() -> Self {
+        Self {
+            keys: None,
+            values: None,
+            evicted: 0,
+        }
+    }
+}
+
+impl<T: Float> KvCache<T> {
+    /// The number of cached positions.
+    pub fn len(&self) -> usize {
+        self.keys.as_ref().map_or(0, |k| k.dim(2))
+    }
+
+    /// The number of positions evicted since the last `clear`.
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// The number of positions appended since the last `clear`.
+    pub fn position(&self) -> usize {
+        self.evicted + self.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn keys(&self) -> Option<&Tensor<T>> {
+        self.keys.as_ref()
+    }
+
+    pub fn values(&self) -> Option<&Tensor<T>> {
+        self.values.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys = None;
+        self.values = None;
+        self.evicted = 0;
+    }
+
+    /// Drops every position except the `keep_first` oldest and the `keep_last` most recent.
+    pub fn evict(&mut self, keep_first: usize, keep_last: usize) {
+        let len = self.len();
+        if keep_first + keep_last >= len {
+            return;
+        }
+        let keep = |t: &Tensor<T>| {
+            Tensor::cat(&[&t.narrow(2, 0, keep_first), &t.narrow(2, len - keep_last, keep_last)], 2)
+        };
+        self.keys = self.keys.as_ref().map(keep);
+        self.values = self.values.as_ref().map(keep);
+        self.evicted += len - keep_first - keep_last;
+    }
 
-pub struct Attention {
-    query_matrix: Linear,
-    key_matrix: Linear,
-    value_matrix: Linear,
-    output_matrix: Linear,
-    dropout: Dropout,
+    /// Appends the keys and values of new positions and returns the full cached keys and values.
+    fn append(&mut self, keys: Tensor<T>, values: Tensor<T>) -> Result<(Tensor<T>, Tensor<T>)> {
+        let (keys, values) = match (&self.keys, &self.values) {
+            (Some(k), Some(v)) => {
+                if k.dim(0) != keys.dim(0) {
+                    let expected = format!("[{}, ..]", k.dim(0));
+                    return Err(Error::shape("KvCache", expected, keys.shape()));
+                }
+                (Tensor::cat(&[k, &keys], 2), Tensor::cat(&[v, &values], 2))
+            }
+            _ => (keys, values),
+        };
+        self.keys = Some(keys.clone());
+        self.values = Some(values.clone());
+        Ok((keys, values))
+    }
 }
 
-impl Attention {
+impl<T: Float> Attention<T> {
+    /// Panics on head counts that do not divide `embedding_dim` and `num_heads`; call
+    /// `Config::validate` first to get an `Error::InvalidConfig` instead.
     pub fn new(config: &Config) -> Self {
+        assert!(
+            config.num_heads > 0 && config.embedding_dim.is_multiple_of(config.num_heads),
+            "embedding_dim ({}) must be divisible by num_heads ({})",
+            config.embedding_dim,
+            config.num_heads
+        );
+        let num_kv_heads = config.kv_heads();
+        assert!(
+            num_kv_heads > 0 && config.num_heads.is_multiple_of(num_kv_heads),
+            "num_heads ({}) must be divisible by num_kv_heads ({})",
+            config.num_heads,
+            num_kv_heads
+        );
+        let head_dim = config.embedding_dim / config.num_heads;
+        let kv_dim = num_kv_heads * head_dim;
+        let rotary = (config.position_encoding == PositionEncoding::Rope).then(|| {
+            let mut rotary = RotaryEmbedding::new(head_dim, config.rope_base);
+            rotary.set_scaling(config.position_scaling);
+            rotary
+        });
+        let alibi_slopes = (config.position_encoding == PositionEncoding::Alibi).then(|| alibi_slopes(config.num_heads));
         let query_matrix = Linear::new(config.embedding_dim, config.embedding_dim);
-        let key_matrix = Linear::new(config.embedding_dim, config.embedding_dim);
-        let value_matrix = Linear::new(config.embedding_dim, config.embedding_dim);
+        let key_matrix = Linear::new(config.embedding_dim, kv_dim);
+        let value_matrix = Linear::new(config.embedding_dim, kv_dim);
         let output_matrix = Linear::new(config.embedding_dim, config.embedding_dim);
         let dropout = Dropout::new(config.dropout_rate);
 
         Self {
+            num_heads: config.num_heads,
+            num_kv_heads,
+            causal: config.causal,
+            rotary,
+            alibi_slopes,
+            block_size: config.attention_block_size,
+            window: config.attention_window,
             query_matrix,
             key_matrix,
             value_matrix,
             output_matrix,
             dropout,
+            attention_weights: None,
+            kv_cache: KvCache::default(),
+            streaming: None,
         }
     }
 
-    pub fn forward(&mut self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let batch_size = input.len();
-        let seq_len = input[0].len();
-        let embedding_dim = self.query_matrix.output_size;
-        let num_heads = embedding_dim / 64;
-
-        let queries = self.query_matrix.forward(input);
-        let keys = self.key_matrix.forward(input);
-        let values = self.value_matrix.forward(input);
-
-        let mut scores = vec![vec![vec![0.0; seq_len]; seq_len]; batch_size];
-        for b in 0..batch_size {
-            for h in 0..num_heads {
-                for i in 0..seq_len {
-                    for j in 0..seq_len {
-                        let query = &queries[b][i * embedding_dim + h * 64..(i + 1) * embedding_dim + h * 64];
-                        let key = &keys[b][j * embedding_dim + h * 64..(j + 1) * embedding_dim + h * 64];
-                        scores[b][i][j] += Self::dot_product(query, key) / (embedding_dim as f64).sqrt();
-                    }
-                }
-            }
+    /// Cross-attention for an encoder-decoder model: queries come from the decoder and keys and
+    /// values from the encoder output, via `forward_cross`. It is never causal or windowed and
+    /// takes no rotary embeddings or ALiBi, since positions in the two sequences are unrelated.
+    pub fn cross(config: &Config) -> Self {
+        Self {
+            causal: false,
+            rotary: None,
+            alibi_slopes: None,
+            window: None,
+            ..Self::new(config)
         }
+    }
 
-        let weights = scores.iter().map(|s| Self::softmax(s)).collect::<Vec<_>>();
-        let dropped_weights = self.dropout.forward(&weights);
+    pub fn num_heads(&self) -> usize {
+        self.num_heads
+    }
 
-        let mut weighted_values = vec![vec![vec![0.0; embedding_dim]; seq_len]; batch_size];
-        for b in 0..batch_size {
-            for h in 0..num_heads {
-                for i in 0..seq_len {
-                    for j in 0..seq_len {
-                        let value = &values[b][j * embedding_dim + h * 64..(j + 1) * embedding_dim + h * 64];
-                        let weight = dropped_weights[b][i][j];
-                        for k in 0..64 {
-                            weighted_values[b][i][h * 64 + k] += value[k] * weight;
-                        }
-                    }
-                }
-            }
+    pub fn num_kv_heads(&self) -> usize {
+        self.num_kv_heads
+    }
+
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+
+    pub fn is_causal(&self) -> bool {
+        self.causal
+    }
+
+    pub fn rotary(&self) -> Option<&RotaryEmbedding> {
+        self.rotary.as_ref()
+    }
+
+    /// Rescales the rotary frequencies, if any, for a longer context. Clears the cache, whose
+    /// keys were rotated under the old scaling.
+    pub fn set_position_scaling(&mut self, scaling: Option<PositionScaling>) {
+        if let Some(rotary) = &mut self.rotary {
+            rotary.set_scaling(scaling);
+        }
+        self.clear_cache();
+    }
+
+    pub fn head_dim(&self) -> usize {
+        self.query_matrix.output_size / self.num_heads
+    }
+
+    pub fn kv_cache(&self) -> &KvCache<T> {
+        &self.kv_cache
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.kv_cache.clear();
+    }
+
+    pub fn streaming(&self) -> Option<Streaming> {
+        self.streaming
+    }
+
+    /// Switches `forward_step` to attention-sink streaming, or back with `None`. Clears the cache.
+    pub fn set_streaming(&mut self, streaming: Option<Streaming>) {
+        if let Some(streaming) = streaming {
+            assert!(streaming.window > 0, "streaming needs a window of at least one position");
+        }
+        self.streaming = streaming;
+        self.clear_cache();
+    }
+
+    /// The position the next `forward_step` token takes: its index in the cache when
+    /// streaming, otherwise the number of positions processed.
+    pub fn next_position(&self) -> usize {
+        match self.streaming {
+            Some(_) => self.kv_cache.len(),
+            None => self.kv_cache.position(),
         }
+    }
+
+    /// Maps `[batch, seq, embedding_dim]` to `[batch, seq, embedding_dim]`.
+    ///
+    /// `mask` is added to the attention scores before the softmax and must broadcast to
+    /// `[batch, num_heads, seq, seq]`; see `padding_mask`.
+    pub fn forward(&mut self, input: &Var<T>, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let queries = self.project_queries(input)?;
+        let (keys, values) = self.project_keys_values(input)?;
+        let (queries, keys) = (self.rotate(&queries, 0), self.rotate(&keys, 0));
+        self.attend(&queries, &keys, &values, 0, mask)
+    }
+
+    /// Attends from `[batch, seq, embedding_dim]` queries over a `[batch, memory_len,
+    /// embedding_dim]` memory such as an encoder output, returning `[batch, seq, embedding_dim]`.
+    ///
+    /// `mask` must broadcast to `[batch, num_heads, seq, memory_len]`; a `padding_mask` of the
+    /// memory hides its padded positions.
+    pub fn forward_cross(&mut self, input: &Var<T>, memory: &Var<T>, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let queries = self.project_queries(input)?;
+        check_memory_batch(input, memory)?;
+        let (keys, values) = self.project_keys_values(memory)?;
+        self.attend(&queries, &keys, &values, 0, mask)
+    }
+
+    /// `forward_cross` for the next decoder positions. The memory's keys and values are projected
+    /// into the KV cache on the first call after `clear_cache` and reused by later calls, so
+    /// `memory` must stay the same until the cache is cleared.
+    pub fn forward_cross_step(&mut self, input: &Var<T>, memory: &Var<T>, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let queries = self.project_queries(input)?;
+        check_memory_batch(input, memory)?;
+        if self.kv_cache.is_empty() {
+            let (keys, values) = self.project_keys_values(memory)?;
+            self.kv_cache.append(keys.value().clone(), values.value().clone())?;
+        }
+        let (keys, values) = match (self.kv_cache.keys(), self.kv_cache.values()) {
+            (Some(keys), Some(values)) => (Var::constant(keys.clone()), Var::constant(values.clone())),
+            _ => unreachable!("the memory was cached above"),
+        };
+        self.attend(&queries, &keys, &values, 0, mask)
+    }
+
+    /// Processes the next `seq` positions of the sequences whose earlier positions are in the KV
+    /// cache, appending their keys and values to it. The output matches the last `seq` rows of
+    /// `forward` over the whole sequence, at the cost of only the new positions.
+    ///
+    /// When streaming, the output instead matches `forward` over the sink positions followed by
+    /// the recent ones, as if the evicted tokens had never been there.
+    pub fn forward_step(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        if let Some(streaming) = self.streaming {
+            return self.forward_streaming(input, streaming);
+        }
+        let past_len = self.kv_cache.len();
+        let queries = self.project_queries(input)?;
+        let (keys, values) = self.project_keys_values(input)?;
+        let position = self.kv_cache.position();
+        let (queries, keys) = (self.rotate(&queries, position), self.rotate(&keys, position));
+        let (keys, values) = self.kv_cache.append(keys.value().clone(), values.value().clone())?;
+        let output = self.attend(&queries, &Var::constant(keys), &Var::constant(values), past_len, None);
+        // The next query reaches back `window - 1` positions; nothing older is needed again.
+        if let Some(window) = self.window {
+            self.kv_cache.evict(0, window - 1);
+        }
+        output
+    }
+
+    fn forward_streaming(&mut self, input: &Var<T>, streaming: Streaming) -> Result<Var<T>> {
+        let past_len = self.kv_cache.len();
+        let queries = self.project_queries(input)?;
+        let (keys, values) = self.project_keys_values(input)?;
+        let (keys, values) = self.kv_cache.append(keys.value().clone(), values.value().clone())?;
+        let queries = self.rotate(&queries, past_len);
+        let keys = self.rotate(&Var::constant(keys), 0);
+        let output = self.attend(&queries, &keys, &Var::constant(values), past_len, None);
+        // Leave room for the next token within `num_sinks + window`.
+        self.kv_cache.evict(streaming.num_sinks, streaming.window - 1);
+        output
+    }
+
+    // Applies the rotary embedding, if any, to `[.., seq, head_dim]` heads starting at `offset`.
+    fn rotate(&self, x: &Var<T>, offset: usize) -> Var<T> {
+        match &self.rotary {
+            Some(rotary) => rotary.apply(x, offset),
+            None => x.clone(),
+        }
+    }
+
+    // Projects `[batch, seq, embedding_dim]` to queries split into `[batch, num_heads, seq,
+    // head_dim]`.
+    fn project_queries(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        Error::check_last_dim("Attention", &input.shape(), Some(3), self.query_matrix.input_size)?;
+        let queries = self.query_matrix.forward(input)?;
+        Ok(self.split_heads(queries, self.num_heads))
+    }
+
+    // Projects `[batch, seq, embedding_dim]` to keys and values split into `[batch,
+    // num_kv_heads, seq, head_dim]`.
+    fn project_keys_values(&mut self, input: &Var<T>) -> Result<(Var<T>, Var<T>)> {
+        Error::check_last_dim("Attention", &input.shape(), Some(3), self.key_matrix.input_size)?;
+        let keys = self.key_matrix.forward(input)?;
+        let values = self.value_matrix.forward(input)?;
+        Ok((self.split_heads(keys, self.num_kv_heads), self.split_heads(values, self.num_kv_heads)))
+    }
 
-        let concat_values = weighted_values.iter().map(|v| v.concat()).collect::<Vec<_>>();
-        self.output_matrix.forward(&concat_values)
+    fn split_heads(&self, x: Var<T>, heads: usize) -> Var<T> {
+        let shape = x.shape();
+        x.reshape(&[shape[0], shape[1], heads, self.head_dim()]).transpose(1, 2)
     }
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let batch_size = grad_output.len();
-        let seq_len = grad_output[0].len() / self.output_matrix.output_size;
+    // Attends the queries of key indices `past_len..` over the keys and values at indices
+    // `0..past_len + seq` and merges the heads back into `[batch, seq, embedding_dim]`.
+    fn attend(&mut self, queries: &Var<T>, keys: &Var<T>, values: &Var<T>, past_len: usize, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let shape = queries.shape();
+        let (batch_size, seq_len, head_dim) = (shape[0], shape[2], shape[3]);
+        let key_len = keys.shape()[2];
         let embedding_dim = self.query_matrix.output_size;
-        let num_heads = embedding_dim / 64;
-
-        let grad_concat_values = self.output_matrix.backward(grad_output);
-        let mut grad_weighted_values = vec![vec![vec![0.0; 64]; seq_len]; batch_size * num_heads];
-        for b in 0..batch_size {
-            for h in 0..num_heads {
-                for i in 0..seq_len {
-                    let start = i * embedding_dim + h * 64;
-                    let end = start + 64;
-                    grad_weighted_values[b * num_heads + h][i] = grad_concat_values[b][start..end].to_vec();
-                }
-            }
+        let scale = 1.0 / (head_dim as f64).sqrt();
+
+        if let Some(block_size) = self.block_size {
+            let bias = ScoreBias {
+                causal: self.causal,
+                past_len,
+                window: self.window,
+                alibi_slopes: self.alibi_slopes.clone(),
+                mask: mask.cloned(),
+            };
+            let rate = self.dropout.rate();
+            let dropout = (self.dropout.is_training() && rate > 0.0).then(|| (rate, rng::with_rng(|rng| rng.next_u64())));
+            self.attention_weights = None;
+            let weighted_values = tiled_attention(queries, keys, values, bias, block_size, dropout)
+                .transpose(1, 2)
+                .reshape(&[batch_size, seq_len, embedding_dim]);
+            return self.output_matrix.forward(&weighted_values);
         }
 
-        let mut grad_values = vec![vec![0.0; embedding_dim]; batch_size * seq_len];
-        let mut grad_dropped_weights = vec![vec![vec![0.0; seq_len]; seq_len]; batch_size];
-        for b in 0..batch_size {
-            for h in 0..num_heads {
-                for i in 0..seq_len {
-                    for j in 0..seq_len {
-                        let grad_weighted_value = &grad_weighted_values[b * num_heads + h][i];
-                        let value = &self.value_matrix.output[b][j * embedding_dim + h * 64..(j + 1) * embedding_dim + h * 64];
-                        for k in 0..64 {
-                            grad_values[b * seq_len + j][h * 64 + k] += grad_weighted_value[k] * self.dropout.mask[b][i][j];
-                            grad_dropped_weights[b][i][j] += grad_weighted_value[k] * value[k];
-                        }
-                    }
-                }
-            }
+        // Each key/value head serves a group of consecutive query heads: batching the groups as
+        // `[batch, num_kv_heads, group, ..]` against `[batch, num_kv_heads, 1, ..]` broadcasts
+        // the shared head without copying it, and backward sums the group's gradients into it.
+        let (kv_heads, group) = (self.num_kv_heads, self.num_heads / self.num_kv_heads);
+        let grouped_queries = queries.reshape(&[batch_size, kv_heads, group, seq_len, head_dim]);
+        let keys = keys.reshape(&[batch_size, kv_heads, 1, key_len, head_dim]);
+        let values = values.reshape(&[batch_size, kv_heads, 1, key_len, head_dim]);
+
+        let mut scores = grouped_queries
+            .matmul(&keys.transpose(3, 4))
+            .reshape(&[batch_size, self.num_heads, seq_len, key_len])
+            .scale(scale);
+        if let Some(slopes) = &self.alibi_slopes {
+            scores = scores.add(&Var::constant(alibi_bias(slopes, seq_len, past_len)));
+        }
+        if self.causal || self.window.is_some() {
+            scores = scores.add(&Var::constant(position_mask(seq_len, past_len, self.causal, self.window)));
+        }
+        if let Some(mask) = mask {
+            scores = scores.add(&Var::constant(mask.clone()));
         }
+        let weights = scores.softmax(3);
+        self.attention_weights = Some(weights.value().clone());
+        let dropped_weights = self.dropout.forward(&weights);
 
-        let grad_weights = self.dropout.backward(&grad_dropped_weights);
-        let grad_scores = grad_weights.iter().map(|s| Self::softmax_backward(s)).collect::<Vec<_>>();
-
-        let mut grad_queries = vec![vec![0.0; embedding_dim]; batch_size * seq_len];
-        let mut grad_keys = vec![vec![0.0; embedding_dim]; batch_size * seq_len];
-        for b in 0..batch_size {
-            for h in 0..num_heads {
-                for i in 0..seq_len {
-                    for j in 0..seq_len {
-                        let grad_score = grad_scores[b][i][j] / (embedding_dim as f64).sqrt();
-                        let query = &self.query_matrix.output[b][i * embedding_dim + h * 64..(i + 1) * embedding_dim + h * 64];
-                        let key = &self.key_matrix.output[b][j * embedding_dim + h * 64..(j + 1) * embedding_dim + h * 64];
-                        for k in 0..64 {
-                            grad_queries[b * seq_len + i][h * 64 + k] += grad_score * key[k];
-                            grad_keys[b * seq_len + j][h * 64 + k] += grad_score * query[k];
-                        }
-                    }
-                }
+        let weighted_values = dropped_weights
+            .reshape(&[batch_size, kv_heads, group, seq_len, key_len])
+            .matmul(&values)
+            .reshape(&[batch_size, self.num_heads, seq_len, head_dim])
+            .transpose(1, 2)
+            .reshape(&[batch_size, seq_len, embedding_dim]);
+        self.output_matrix.forward(&weighted_values)
+    }
+}
+
+/// A `[seq_len, seq_len]` additive mask that is `0` on and below the diagonal and `-inf` above
+/// it, so after the softmax no query puts weight on a later key.
+pub fn causal_mask<T: Float>(seq_len: usize) -> Tensor<T> {
+    position_mask(seq_len, 0, true, None)
+}
+
+/// The `[seq_len, seq_len]` mask of sliding-window attention: `0` for keys fewer than `window`
+/// positions away (and, if `causal`, not later than the query), `-inf` elsewhere.
+pub fn window_mask<T: Float>(seq_len: usize, window: usize, causal: bool) -> Tensor<T> {
+    position_mask(seq_len, 0, causal, Some(window))
+}
+
+// The mask for `seq_len` queries at key indices `past_len..` over keys `0..past_len + seq_len`.
+fn position_mask<T: Float>(seq_len: usize, past_len: usize, causal: bool, window: Option<usize>) -> Tensor<T> {
+    let key_len = past_len + seq_len;
+    let mut mask = Tensor::zeros(&[seq_len, key_len]);
+    for i in 0..seq_len {
+        let query = past_len + i;
+        for j in 0..key_len {
+            let later = causal && j > query;
+            let outside = window.is_some_and(|w| query.abs_diff(j) >= w);
+            if later || outside {
+                mask.set(&[i, j], T::neg_infinity());
             }
         }
+    }
+    mask
+}
 
-        let grad_input = self.query_matrix.backward(&grad_queries);
-        self.key_matrix.backward(&grad_keys);
-        self.value_matrix.backward(&grad_values);
-
-        grad_input
+// Cross-attention pairs each query sequence with the memory at the same batch index.
+fn check_memory_batch<T: Float>(input: &Var<T>, memory: &Var<T>) -> Result<()> {
+    let (batch_size, memory_shape) = (input.shape()[0], memory.shape());
+    if memory_shape.len() != 3 || memory_shape[0] != batch_size {
+        return Err(Error::shape("Attention memory", format!("[{}, _, _]", batch_size), &memory_shape));
     }
+    Ok(())
+}
 
-    fn dot_product(v1: &[f64], v2: &[f64]) -> f64 {
-        v1.iter().zip(v2).map(|(&x, &y)| x * y).sum()
+/// Turns per-sample `[batch][seq]` flags (`true` for real tokens, `false` for padding) into a
+/// `[batch, 1, 1, seq]` additive mask that hides padded keys from every query.
+pub fn padding_mask<T: Float>(attention_mask: &[Vec<bool>]) -> Tensor<T> {
+    let batch_size = attention_mask.len();
+    let seq_len = attention_mask.first().map_or(0, |row| row.len());
+    let data = attention_mask
+        .iter()
+        .flat_map(|row| row.iter().map(|&keep| if keep { T::zero() } else { T::neg_infinity() }))
+        .collect();
+    Tensor::new(data, &[batch_size, 1, 1, seq_len])
+}
+
+impl<T: Float> Module<T> for Attention<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("query_matrix", self.query_matrix.named_parameters()));
+        params.extend(prefixed("key_matrix", self.key_matrix.named_parameters()));
+        params.extend(prefixed("value_matrix", self.value_matrix.named_parameters()));
+        params.extend(prefixed("output_matrix", self.output_matrix.named_parameters()));
+        params
     }
 
-    fn softmax(scores: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let max_scores = scores.iter().map(|s| s.iter().cloned().fold(f64::NEG_INFINITY, f64::max)).collect::<Vec<_>>();
-        let exp_scores = scores.iter().zip(&max_scores).map(|(s, &m)| s.iter().map(|&x| (x - m).exp()).collect::<Vec<_>>()).collect::<Vec<_>>();
-        let sum_exp_scores = exp_scores.iter().map(|s| s.iter().sum::<f64>()).collect::<Vec<_>>();
-        exp_scores.iter().zip(&sum_exp_scores).map(|(s, &d)| s.iter().map(|&x| x / d).collect()).collect()
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("query_matrix", self.query_matrix.named_parameters_mut()));
+        params.extend(prefixed("key_matrix", self.key_matrix.named_parameters_mut()));
+        params.extend(prefixed("value_matrix", self.value_matrix.named_parameters_mut()));
+        params.extend(prefixed("output_matrix", self.output_matrix.named_parameters_mut()));
+        params
     }
 
-    fn softmax_backward(grad_output: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let softmax_output = Self::softmax(grad_output);
-        grad_output.iter().zip(&softmax_output).map(|(go, so)| so.iter().zip(go).map(|(&s, &g)| s * (g - Self::dot_product(so, go))).collect()).collect()
+    fn set_training(&mut self, training: bool) {
+        self.dropout.set_training(training);
     }
-}
\ No newline at end of file
+}
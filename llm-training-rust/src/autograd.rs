@@ -0,0 +1,347 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::tensor::Tensor;
+
+type BackwardFn<T> = Box<dyn Fn(&Tensor<T>) -> Vec<Tensor<T>>>;
+
+struct Node<T: Float> {
+    value: RefCell<Tensor<T>>,
+    grad: RefCell<Option<Tensor<T>>>,
+    requires_grad: bool,
+    parents: Vec<Var<T>>,
+    backward: Option<BackwardFn<T>>,
+}
+
+/// A tensor recorded on the autograd graph.
+///
+/// Every op on a `Var` that depends on a trainable leaf records its inputs and a closure that maps
+/// the output gradient to input gradients. `backward` replays those records in reverse
+/// topological order and accumulates the result into the `grad` of each trainable leaf.
+#[derive(Clone)]
+pub struct Var<T: Float = f32>(Rc<Node<T>>);
+
+impl<T: Float> fmt::Debug for Var<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Var")
+            .field("shape", &self.shape())
+            .field("requires_grad", &self.0.requires_grad)
+            .finish()
+    }
+}
+
+impl<T: Float> Var<T> {
+    fn from_node(value: Tensor<T>, requires_grad: bool, parents: Vec<Var<T>>, backward: Option<BackwardFn<T>>) -> Self {
+        Var(Rc::new(Node {
+            value: RefCell::new(value),
+            grad: RefCell::new(None),
+            requires_grad,
+            parents,
+            backward,
+        }))
+    }
+
+    /// A trainable leaf; gradients flowing into it are accumulated in `grad()`.
+    pub fn leaf(value: Tensor<T>) -> Self {
+        Self::from_node(value, true, Vec::new(), None)
+    }
+
+    /// A value that is not differentiated, such as an input batch or a mask.
+    pub fn constant(value: Tensor<T>) -> Self {
+        Self::from_node(value, false, Vec::new(), None)
+    }
+
+    pub(crate) fn from_op(value: Tensor<T>, parents: Vec<Var<T>>, backward: impl Fn(&Tensor<T>) -> Vec<Tensor<T>> + 'static) -> Self {
+        if !parents.iter().any(|p| p.requires_grad()) {
+            return Self::constant(value);
+        }
+        Self::from_node(value, true, parents, Some(Box::new(backward)))
+    }
+
+    fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    pub fn requires_grad(&self) -> bool {
+        self.0.requires_grad
+    }
+
+    pub fn value(&self) -> Ref<'_, Tensor<T>> {
+        self.0.value.borrow()
+    }
+
+    pub(crate) fn value_mut(&self) -> RefMut<'_, Tensor<T>> {
+        self.0.value.borrow_mut()
+    }
+
+    pub fn shape(&self) -> Vec<usize> {
+        self.value().shape().to_vec()
+    }
+
+    pub fn grad(&self) -> Option<Tensor<T>> {
+        self.0.grad.borrow().clone()
+    }
+
+    pub fn zero_grad(&self) {
+        *self.0.grad.borrow_mut() = None;
+    }
+
+    fn topological_order(&self) -> Vec<Var<T>> {
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((var, expanded)) = stack.pop() {
+            if expanded {
+                order.push(var);
+                continue;
+            }
+            if !visited.insert(var.id()) {
+                continue;
+            }
+            stack.push((var.clone(), true));
+            for parent in &var.0.parents {
+                if parent.requires_grad() && !visited.contains(&parent.id()) {
+                    stack.push((parent.clone(), false));
+                }
+            }
+        }
+        order
+    }
+
+    /// Back-propagates from this value, seeding it with a gradient of ones.
+    pub fn backward(&self) {
+        self.backward_with(Tensor::ones(self.value().shape()));
+    }
+
+    /// Back-propagates `grad`, the gradient of some downstream objective with respect to this value.
+    pub fn backward_with(&self, grad: Tensor<T>) {
+        assert_eq!(grad.shape(), self.value().shape(), "seed gradient does not match the value shape");
+        if !self.requires_grad() {
+            return;
+        }
+        let mut grads: HashMap<usize, Tensor<T>> = HashMap::new();
+        grads.insert(self.id(), grad);
+
+        for var in self.topological_order().iter().rev() {
+            let Some(grad) = grads.remove(&var.id()) else {
+                continue;
+            };
+            let Some(backward) = &var.0.backward else {
+                let mut leaf_grad = var.0.grad.borrow_mut();
+                *leaf_grad = Some(match leaf_grad.take() {
+                    Some(existing) => existing.add(&grad),
+                    None => grad,
+                });
+                continue;
+            };
+            for (parent, parent_grad) in var.0.parents.iter().zip(backward(&grad)) {
+                if !parent.requires_grad() {
+                    continue;
+                }
+                match grads.entry(parent.id()) {
+                    Entry::Occupied(mut e) => {
+                        let sum = e.get().add(&parent_grad);
+                        e.insert(sum);
+                    }
+                    Entry::Vacant(e) => {
+                        e.insert(parent_grad);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn add(&self, other: &Var<T>) -> Var<T> {
+        let (a_shape, b_shape) = (self.shape(), other.shape());
+        let value = self.value().add(&other.value());
+        Self::from_op(value, vec![self.clone(), other.clone()], move |g| {
+            vec![g.sum_to(&a_shape), g.sum_to(&b_shape)]
+        })
+    }
+
+    pub fn sub(&self, other: &Var<T>) -> Var<T> {
+        let (a_shape, b_shape) = (self.shape(), other.shape());
+        let value = self.value().sub(&other.value());
+        Self::from_op(value, vec![self.clone(), other.clone()], move |g| {
+            vec![g.sum_to(&a_shape), g.scale(-1.0).sum_to(&b_shape)]
+        })
+    }
+
+    pub fn mul(&self, other: &Var<T>) -> Var<T> {
+        let (a, b) = (self.value().clone(), other.value().clone());
+        let value = a.mul(&b);
+        Self::from_op(value, vec![self.clone(), other.clone()], move |g| {
+            vec![g.mul(&b).sum_to(a.shape()), g.mul(&a).sum_to(b.shape())]
+        })
+    }
+
+    pub fn div(&self, other: &Var<T>) -> Var<T> {
+        let (a, b) = (self.value().clone(), other.value().clone());
+        let value = a.div(&b);
+        Self::from_op(value, vec![self.clone(), other.clone()], move |g| {
+            let grad_b = g.mul(&a).zip_map(&b, |x, y| -x / (y * y));
+            vec![g.div(&b).sum_to(a.shape()), grad_b.sum_to(b.shape())]
+        })
+    }
+
+    pub fn scale(&self, factor: f64) -> Var<T> {
+        let value = self.value().scale(factor);
+        Self::from_op(value, vec![self.clone()], move |g| vec![g.scale(factor)])
+    }
+
+    /// Applies `f` elementwise; `df` is its derivative, evaluated at the input.
+    pub fn map(&self, f: impl Fn(T) -> T, df: impl Fn(T) -> T + 'static) -> Var<T> {
+        let input = self.value().clone();
+        let value = input.map(f);
+        Self::from_op(value, vec![self.clone()], move |g| vec![g.zip_map(&input, |g, x| g * df(x))])
+    }
+
+    pub fn matmul(&self, other: &Var<T>) -> Var<T> {
+        let (a, b) = (self.value().clone(), other.value().clone());
+        let value = a.matmul(&b);
+        Self::from_op(value, vec![self.clone(), other.clone()], move |g| {
+            let a_t = a.transpose(a.ndim() - 2, a.ndim() - 1);
+            let b_t = b.transpose(b.ndim() - 2, b.ndim() - 1);
+            vec![g.matmul(&b_t).sum_to(a.shape()), a_t.matmul(g).sum_to(b.shape())]
+        })
+    }
+
+    pub fn transpose(&self, a: usize, b: usize) -> Var<T> {
+        let value = self.value().transpose(a, b).contiguous();
+        Self::from_op(value, vec![self.clone()], move |g| vec![g.transpose(a, b).contiguous()])
+    }
+
+    pub fn reshape(&self, shape: &[usize]) -> Var<T> {
+        let original = self.shape();
+        let value = self.value().reshape(shape);
+        Self::from_op(value, vec![self.clone()], move |g| vec![g.reshape(&original)])
+    }
+
+    pub fn narrow(&self, axis: usize, start: usize, len: usize) -> Var<T> {
+        let original = self.shape();
+        let value = self.value().narrow(axis, start, len);
+        Self::from_op(value, vec![self.clone()], move |g| {
+            let mut before = original.clone();
+            before[axis] = start;
+            let mut after = original.clone();
+            after[axis] = original[axis] - start - len;
+            vec![Tensor::cat(&[&Tensor::zeros(&before), g, &Tensor::zeros(&after)], axis)]
+        })
+    }
+
+    pub fn cat(vars: &[&Var<T>], axis: usize) -> Var<T> {
+        let lengths = vars.iter().map(|v| v.value().dim(axis)).collect::<Vec<_>>();
+        let value = {
+            let values = vars.iter().map(|v| v.value()).collect::<Vec<_>>();
+            Tensor::cat(&values.iter().map(|v| &**v).collect::<Vec<_>>(), axis)
+        };
+        let parents = vars.iter().map(|&v| v.clone()).collect();
+        Self::from_op(value, parents, move |g| {
+            let mut start = 0;
+            lengths
+                .iter()
+                .map(|&len| {
+                    let part = g.narrow(axis, start, len);
+                    start += len;
+                    part
+                })
+                .collect()
+        })
+    }
+
+    pub fn sum(&self) -> Var<T> {
+        let shape = self.shape();
+        let value = Tensor::scalar(self.value().sum());
+        Self::from_op(value, vec![self.clone()], move |g| vec![g.broadcast_to(&shape)])
+    }
+
+    pub fn sum_axis(&self, axis: usize, keepdim: bool) -> Var<T> {
+        let shape = self.shape();
+        let value = self.value().sum_axis(axis, keepdim);
+        Self::from_op(value, vec![self.clone()], move |g| {
+            let mut kept = shape.clone();
+            kept[axis] = 1;
+            vec![g.reshape(&kept).broadcast_to(&shape)]
+        })
+    }
+
+    pub fn mean_axis(&self, axis: usize, keepdim: bool) -> Var<T> {
+        let n = self.value().dim(axis) as f64;
+        self.sum_axis(axis, keepdim).scale(1.0 / n)
+    }
+
+    pub fn softmax(&self, axis: usize) -> Var<T> {
+        let output = self.value().softmax(axis);
+        let value = output.clone();
+        Self::from_op(value, vec![self.clone()], move |g| {
+            let dot = g.mul(&output).sum_axis(axis, true);
+            vec![output.mul(&g.sub(&dot))]
+        })
+    }
+
+    pub fn log_softmax(&self, axis: usize) -> Var<T> {
+        let input = self.value().clone();
+        let max = input.max_axis(axis, true);
+        let shifted = input.sub(&max);
+        let log_sum = shifted.map(T::exp).sum_axis(axis, true).map(T::ln);
+        let value = shifted.sub(&log_sum);
+        let softmax = value.map(T::exp);
+        Self::from_op(value, vec![self.clone()], move |g| {
+            vec![g.sub(&softmax.mul(&g.sum_axis(axis, true)))]
+        })
+    }
+
+    /// Picks rows of a `[rows, dim]` table, giving `[indices.len(), dim]`.
+    pub fn select_rows(&self, indices: &[usize]) -> Var<T> {
+        let shape = self.shape();
+        assert_eq!(shape.len(), 2, "select_rows expects a 2-d table, got {:?}", shape);
+        let dim = shape[1];
+        let value = {
+            let table = self.value();
+            let table = table.data();
+            let mut data = Vec::with_capacity(indices.len() * dim);
+            for &idx in indices {
+                data.extend_from_slice(&table[idx * dim..(idx + 1) * dim]);
+            }
+            Tensor::new(data, &[indices.len(), dim])
+        };
+        let indices = indices.to_vec();
+        Self::from_op(value, vec![self.clone()], move |g| {
+            let mut grad = Tensor::zeros(&shape);
+            let g = g.contiguous();
+            let table = grad.data_mut();
+            for (grad_row, &idx) in g.data().chunks(dim).zip(&indices) {
+                for (t, &v) in table[idx * dim..(idx + 1) * dim].iter_mut().zip(grad_row) {
+                    *t += v;
+                }
+            }
+            vec![grad]
+        })
+    }
+
+    /// Picks one entry per position along the last axis, dropping that axis.
+    pub fn gather_last(&self, indices: &[usize]) -> Var<T> {
+        let shape = self.shape();
+        let n = *shape.last().expect("gather_last on a scalar");
+        assert_eq!(indices.len() * n, self.value().numel(), "gather_last needs one index per row of {:?}", shape);
+        let value = {
+            let input = self.value().contiguous();
+            let data = indices.iter().enumerate().map(|(row, &idx)| input.data()[row * n + idx]).collect();
+            Tensor::new(data, &shape[..shape.len() - 1])
+        };
+        let indices = indices.to_vec();
+        Self::from_op(value, vec![self.clone()], move |g| {
+            let mut grad = Tensor::zeros(&shape);
+            let g = g.contiguous();
+            let data = grad.data_mut();
+            for (row, (&idx, &v)) in indices.iter().zip(g.data()).enumerate() {
+                data[row * n + idx] += v;
+            }
+            vec![grad]
+        })
+    }
+}
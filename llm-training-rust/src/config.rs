@@ -1,23 +1,275 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::attention::Streaming;
+use crate::data_loader::DataLoader;
+use crate::activation::Activation;
+use crate::error::{Error, Result};
+use crate::feed_forward::FfnType;
+use crate::float::DType;
+use crate::positional_encoding::{PositionEncoding, PositionScaling};
+use crate::tokenizer::Tokenizer;
+use crate::utils::{read_text_file, write_text_file};
+
+/// Model architecture and training hyperparameters.
+///
+/// Unknown keys are rejected when parsing, so a misspelt field is an error instead of silently
+/// falling back to a default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub vocab_size: usize,
     pub max_seq_len: usize,
     pub embedding_dim: usize,
     pub num_layers: usize,
     pub num_heads: usize,
+    /// Key/value heads shared by groups of `num_heads / num_kv_heads` query heads: grouped-query
+    /// attention, or multi-query attention with `Some(1)`. `None` gives every query head its own.
+    #[serde(default)]
+    pub num_kv_heads: Option<usize>,
     pub feed_forward_dim: usize,
+    /// The plain GELU MLP or a gated variant (SwiGLU, GeGLU), which uses two thirds of
+    /// `feed_forward_dim` as its hidden width.
+    #[serde(default)]
+    pub ffn_type: FfnType,
+    /// The feed-forward nonlinearity; unset picks the one `ffn_type` is named for.
+    #[serde(default)]
+    pub activation: Option<Activation>,
     pub dropout_rate: f64,
     pub learning_rate: f64,
     pub batch_size: usize,
     pub num_epochs: usize,
     pub checkpoint_interval: usize,
+    /// Element type for parameters, activations and optimizer state.
+    #[serde(default)]
+    pub dtype: DType,
+    /// Seeds weight initialization, dropout, data shuffling and sampling.
+    #[serde(default)]
+    pub seed: u64,
+    /// Stops attention from looking at later positions. Needed for next-token training; turn it
+    /// off only for bidirectional encoders.
+    #[serde(default = "default_causal")]
+    pub causal: bool,
+    /// Additive sinusoidal or learned position vectors, rotary embeddings or ALiBi score biases.
+    #[serde(default)]
+    pub position_encoding: PositionEncoding,
+    /// Restrict attention to keys fewer than this many positions away (sliding-window attention).
+    #[serde(default)]
+    pub attention_window: Option<usize>,
+    /// With `attention_window`, make every n-th layer (the n-th, 2n-th, ...) attend globally; `2`
+    /// alternates local and global layers.
+    #[serde(default)]
+    pub global_attention_every: Option<usize>,
+    /// Generate with attention sinks and a rolling KV window instead of rebuilding the caches
+    /// when they reach `max_seq_len`. Needs rope or alibi position encoding.
+    #[serde(default)]
+    pub streaming: Option<Streaming>,
+    /// How many tokens `Model::generate` samples; `max_seq_len` if unset.
+    #[serde(default)]
+    pub max_new_tokens: Option<usize>,
+    /// Use `tiled_attention` with key blocks of this size instead of materializing the full
+    /// score matrix. Trades speed for memory that grows linearly with the sequence length.
+    #[serde(default)]
+    pub attention_block_size: Option<usize>,
+    /// Base of the rotary frequencies; larger values rotate the later dimensions more slowly.
+    #[serde(default = "default_rope_base")]
+    pub rope_base: f64,
+    /// Stretch sinusoidal or rotary positions over a longer context than the model was trained
+    /// on; see `PositionScaling`.
+    #[serde(default)]
+    pub position_scaling: Option<PositionScaling>,
+    /// Start training from this checkpoint instead of fresh weights, with this config's
+    /// `max_seq_len` and `position_scaling` applied to it (see `Model::from_checkpoint`).
+    #[serde(default)]
+    pub init_checkpoint: Option<String>,
+}
+
+fn default_causal() -> bool {
+    true
+}
+
+fn default_rope_base() -> f64 {
+    10000.0
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            vocab_size: 100,
+            max_seq_len: 20,
+            embedding_dim: 32,
+            num_layers: 2,
+            num_heads: 4,
+            num_kv_heads: None,
+            feed_forward_dim: 64,
+            ffn_type: FfnType::Mlp,
+            activation: None,
+            dropout_rate: 0.1,
+            learning_rate: 0.001,
+            batch_size: 2,
+            num_epochs: 1,
+            checkpoint_interval: 10,
+            dtype: DType::F32,
+            seed: 0,
+            causal: true,
+            position_encoding: PositionEncoding::Sinusoidal,
+            rope_base: default_rope_base(),
+            attention_block_size: None,
+            attention_window: None,
+            global_attention_every: None,
+            streaming: None,
+            max_new_tokens: None,
+            position_scaling: None,
+            init_checkpoint: None,
+        }
+    }
 }
 
 impl Config {
-    pub fn from_file(file_path: &str) -> Self {
-        let json_str = std::fs::read_to_string(file_path).expect("Failed to read config file");
-        serde_json::from_str(&json_str).expect("Failed to parse config JSON")
+    pub fn from_json(json_str: &str) -> Result<Self> {
+        Self::parse(json_str, "<string>")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize config to JSON")
+    }
+
+    pub fn from_file(file_path: &str) -> Result<Self> {
+        let json_str = read_text_file(file_path)?;
+        Self::parse(&json_str, file_path)
+    }
+
+    pub fn save_to_file(&self, file_path: &str) -> Result<()> {
+        write_text_file(file_path, &self.to_json())
     }
+
+    fn parse(json_str: &str, path: &str) -> Result<Self> {
+        serde_json::from_str(json_str).map_err(|e| Error::Parse {
+            path: path.to_string(),
+            what: "config JSON".to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// The number of key/value heads, `num_kv_heads` or else `num_heads`.
+    pub fn kv_heads(&self) -> usize {
+        self.num_kv_heads.unwrap_or(self.num_heads)
+    }
+
+    /// The feed-forward activation, `activation` or else the default of `ffn_type`.
+    pub fn feed_forward_activation(&self) -> Activation {
+        self.activation.unwrap_or(self.ffn_type.default_activation())
+    }
+
+    /// The attention window of layer `layer` (from 0): `attention_window`, unless the layer is
+    /// one of every `global_attention_every` that attend to the whole sequence.
+    pub fn layer_attention_window(&self, layer: usize) -> Option<usize> {
+        let global = self.global_attention_every.is_some_and(|n| (layer + 1).is_multiple_of(n));
+        if global { None } else { self.attention_window }
+    }
+
+    /// Rejects settings that cannot build or train a model.
+    pub fn validate(&self) -> Result<()> {
+        let sizes = [
+            ("vocab_size", self.vocab_size),
+            ("max_seq_len", self.max_seq_len),
+            ("embedding_dim", self.embedding_dim),
+            ("num_heads", self.num_heads),
+            ("num_kv_heads", self.kv_heads()),
+            ("feed_forward_dim", self.feed_forward_dim),
+            ("batch_size", self.batch_size),
+            ("checkpoint_interval", self.checkpoint_interval),
+        ];
+        for (name, value) in sizes {
+            if value == 0 {
+                return Err(invalid(format!("{} must be positive", name)));
+            }
+        }
+        if !self.embedding_dim.is_multiple_of(self.num_heads) {
+            return Err(invalid(format!(
+                "embedding_dim ({}) must be divisible by num_heads ({})",
+                self.embedding_dim, self.num_heads
+            )));
+        }
+        if !self.num_heads.is_multiple_of(self.kv_heads()) {
+            return Err(invalid(format!(
+                "num_heads ({}) must be divisible by num_kv_heads ({})",
+                self.num_heads,
+                self.kv_heads()
+            )));
+        }
+        if self.position_encoding == PositionEncoding::Rope {
+            let head_dim = self.embedding_dim / self.num_heads;
+            if !head_dim.is_multiple_of(2) {
+                return Err(invalid(format!("rope needs an even head dimension, got {}", head_dim)));
+            }
+            if !(self.rope_base > 1.0 && self.rope_base.is_finite()) {
+                return Err(invalid(format!("rope_base must be greater than 1, got {}", self.rope_base)));
+            }
+        }
+        let optional_sizes = [
+            ("attention_block_size", self.attention_block_size),
+            ("attention_window", self.attention_window),
+            ("global_attention_every", self.global_attention_every),
+        ];
+        for (name, value) in optional_sizes {
+            if value == Some(0) {
+                return Err(invalid(format!("{} must be positive", name)));
+            }
+        }
+        if self.streaming.is_some() && !matches!(self.position_encoding, PositionEncoding::Rope | PositionEncoding::Alibi) {
+            return Err(invalid("streaming needs rope or alibi position encoding".to_string()));
+        }
+        if let Some(streaming) = self.streaming
+            && (streaming.window == 0 || streaming.num_sinks + streaming.window > self.max_seq_len)
+        {
+            return Err(invalid(format!(
+                "streaming needs 0 < window and num_sinks + window <= max_seq_len, got {} + {} for {}",
+                streaming.num_sinks, streaming.window, self.max_seq_len
+            )));
+        }
+        if let Some(scaling) = self.position_scaling {
+            if !matches!(self.position_encoding, PositionEncoding::Sinusoidal | PositionEncoding::Rope) {
+                return Err(invalid("position_scaling applies to sinusoidal and rope position encodings".to_string()));
+            }
+            scaling.check()?;
+        }
+        if self.global_attention_every.is_some() && self.attention_window.is_none() {
+            return Err(invalid("global_attention_every needs an attention_window".to_string()));
+        }
+        if !(0.0..1.0).contains(&self.dropout_rate) {
+            return Err(invalid(format!("dropout_rate must be in [0, 1), got {}", self.dropout_rate)));
+        }
+        if !(self.learning_rate > 0.0 && self.learning_rate.is_finite()) {
+            return Err(invalid(format!("learning_rate must be positive, got {}", self.learning_rate)));
+        }
+        Ok(())
+    }
+
+    /// `validate`, plus checks against the tokenizer and training data the config will be used with.
+    pub fn validate_with(&self, tokenizer: &Tokenizer, data_loader: &DataLoader) -> Result<()> {
+        self.validate()?;
+        if tokenizer.vocab_size() != self.vocab_size {
+            return Err(invalid(format!(
+                "vocab_size ({}) does not match the tokenizer's {} tokens",
+                self.vocab_size,
+                tokenizer.vocab_size()
+            )));
+        }
+        // One batch needs `batch_size` windows plus the final shifted target.
+        let needed = self.batch_size * self.max_seq_len + 1;
+        if data_loader.num_tokens() < needed {
+            return Err(invalid(format!(
+                "batch_size * max_seq_len ({} * {}) needs at least {} tokens, the dataset has {}",
+                self.batch_size,
+                self.max_seq_len,
+                needed,
+                data_loader.num_tokens()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidConfig(message)
 }
\ No newline at end of file
@@ -1,64 +1,257 @@
+use rand::seq::SliceRandom;
+
+use crate::error::{Error, Result};
+use crate::rng;
 use crate::tokenizer::Tokenizer;
 
+/// Cuts a token stream into non-overlapping `seq_len` windows and groups them into batches.
 pub struct DataLoader {
     data: Vec<usize>,
     pub batch_size: usize,
     pub seq_len: usize,
-    idx: usize,
+    /// Visit the windows in a fresh random order on every call to `iter`.
+    pub shuffle: bool,
+    order: Vec<usize>,
+    pad_id: usize,
 }
 
 impl DataLoader {
-    pub fn new(file_path: &str, batch_size: usize, seq_len: usize, tokenizer: &Tokenizer) -> Self {
-        let text = std::fs::read_to_string(file_path).expect("Failed to read data file");
+    /// A `batch_size` or `seq_len` of 0 is an `Error::InvalidConfig`.
+    pub fn new(file_path: &str, batch_size: usize, seq_len: usize, tokenizer: &Tokenizer) -> Result<Self> {
+        check_sizes(batch_size, "seq_len", seq_len)?;
+        let text = std::fs::read_to_string(file_path).map_err(|e| Error::io(file_path, e))?;
         let data = tokenizer.encode(&text);
-        Self {
+        let num_windows = data.len().saturating_sub(1) / seq_len;
+        Ok(Self {
             data,
             batch_size,
             seq_len,
-            idx: 0,
-        }
+            shuffle: false,
+            order: (0..num_windows).collect(),
+            pad_id: tokenizer.pad_id,
+        })
     }
 
-    pub fn iter(&mut self) -> DataLoaderIter {
+    /// Starts a pass over the data, reshuffling the window order first if `shuffle` is set. The
+    /// iterator wraps around, so callers bound it with `take(self.len())` for one epoch.
+    pub fn iter(&mut self) -> DataLoaderIter<'_> {
+        if self.shuffle {
+            rng::with_rng(|rng| self.order.shuffle(rng));
+        }
         DataLoaderIter {
             data_loader: self,
             idx: 0,
         }
     }
 
+    /// The length of the tokenized dataset.
+    pub fn num_tokens(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The tokenizer's `<pad>` id, which `Model::fit` excludes from the loss.
+    pub fn pad_id(&self) -> usize {
+        self.pad_id
+    }
+
     pub fn len(&self) -> usize {
-        (self.data.len() - 1) / (self.batch_size * self.seq_len)
+        self.order.len() / self.batch_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
 pub struct DataLoaderIter<'a> {
-    data_loader: &'a mut DataLoader,
+    data_loader: &'a DataLoader,
     idx: usize,
 }
 
 impl<'a> Iterator for DataLoaderIter<'a> {
-    type Item = (Vec<usize>, Vec<usize>);
+    /// `(input, target)` as `[batch_size][seq_len]` token ids; targets are inputs shifted by one.
+    type Item = (Vec<Vec<usize>>, Vec<Vec<usize>>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx >= self.data_loader.data.len() - 1 {
-            self.data_loader.idx = 0;
-            self.idx = 0;
-        }
-
         let batch_size = self.data_loader.batch_size;
         let seq_len = self.data_loader.seq_len;
+        let order = &self.data_loader.order;
+        if order.len() < batch_size {
+            return None;
+        }
+        if self.idx + batch_size > order.len() {
+            self.idx = 0;
+        }
 
-        let mut batch_input = Vec::with_capacity(batch_size * seq_len);
-        let mut batch_target = Vec::with_capacity(batch_size * seq_len);
+        let mut batch_input = Vec::with_capacity(batch_size);
+        let mut batch_target = Vec::with_capacity(batch_size);
 
-        for _ in 0..batch_size {
-            let start = self.idx;
+        for &window in &order[self.idx..self.idx + batch_size] {
+            let start = window * seq_len;
             let end = start + seq_len;
-            batch_input.extend_from_slice(&self.data_loader.data[start..end]);
-            batch_target.extend_from_slice(&self.data_loader.data[start + 1..=end]);
-            self.idx += seq_len;
+            batch_input.push(self.data_loader.data[start..end].to_vec());
+            batch_target.push(self.data_loader.data[start + 1..=end].to_vec());
         }
+        self.idx += batch_size;
 
         Some((batch_input, batch_target))
     }
-}
\ No newline at end of file
+}
+
+/// Batches `(source, target)` sentence pairs for `EncoderDecoderModel`, read from a file with one
+/// pair per line and a tab between source and target.
+///
+/// The decoder is trained with teacher forcing: it reads `<eos>` followed by the target, which
+/// doubles as its start token, and predicts the target followed by `<eos>`.
+pub struct PairDataLoader {
+    pairs: Vec<(Vec<usize>, Vec<usize>)>,
+    pub batch_size: usize,
+    /// Visit the pairs in a fresh random order on every call to `iter`.
+    pub shuffle: bool,
+    order: Vec<usize>,
+    eos_id: usize,
+    pad_id: usize,
+}
+
+impl PairDataLoader {
+    /// Sources are cut to `max_seq_len` tokens and targets to `max_seq_len - 1`, leaving room
+    /// for `<eos>`. Blank lines are skipped; a line without a tab is an `Error::Parse`, and a
+    /// `batch_size` or `max_seq_len` of 0 an `Error::InvalidConfig`.
+    pub fn new(file_path: &str, batch_size: usize, max_seq_len: usize, tokenizer: &Tokenizer) -> Result<Self> {
+        check_sizes(batch_size, "max_seq_len", max_seq_len)?;
+        let text = std::fs::read_to_string(file_path).map_err(|e| Error::io(file_path, e))?;
+        let pairs = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let (source, target) = line.split_once('\t').ok_or_else(|| Error::Parse {
+                    path: file_path.to_string(),
+                    what: "sentence pairs".to_string(),
+                    message: format!("line {} has no tab between source and target", i + 1),
+                })?;
+                let mut source = tokenizer.encode(source);
+                let mut target = tokenizer.encode(target);
+                source.truncate(max_seq_len);
+                target.truncate(max_seq_len.saturating_sub(1));
+                Ok((source, target))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            order: (0..pairs.len()).collect(),
+            pairs,
+            batch_size,
+            shuffle: false,
+            eos_id: tokenizer.eos_id,
+            pad_id: tokenizer.pad_id,
+        })
+    }
+
+    /// Starts a pass over the pairs, reshuffling them first if `shuffle` is set. Like
+    /// `DataLoader::iter`, the iterator wraps around.
+    pub fn iter(&mut self) -> PairDataLoaderIter<'_> {
+        if self.shuffle {
+            rng::with_rng(|rng| self.order.shuffle(rng));
+        }
+        PairDataLoaderIter {
+            data_loader: self,
+            idx: 0,
+        }
+    }
+
+    pub fn num_pairs(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// The tokenizer's `<pad>` id, which right-pads every field of a batch.
+    pub fn pad_id(&self) -> usize {
+        self.pad_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len() / self.batch_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// One batch of `PairDataLoader`, each field `[batch_size][..]` and right-padded with `<pad>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairBatch {
+    pub source: Vec<Vec<usize>>,
+    /// `true` for real source tokens, for `EncoderDecoderModel::forward`.
+    pub source_mask: Vec<Vec<bool>>,
+    /// `<eos>` followed by the target tokens.
+    pub input: Vec<Vec<usize>>,
+    /// The target tokens followed by `<eos>`. The padding is `<pad>`, which
+    /// `EncoderDecoderModel::fit` excludes from the loss.
+    pub target: Vec<Vec<usize>>,
+}
+
+pub struct PairDataLoaderIter<'a> {
+    data_loader: &'a PairDataLoader,
+    idx: usize,
+}
+
+impl<'a> Iterator for PairDataLoaderIter<'a> {
+    type Item = PairBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let loader = self.data_loader;
+        let batch_size = loader.batch_size;
+        if loader.order.len() < batch_size {
+            return None;
+        }
+        if self.idx + batch_size > loader.order.len() {
+            self.idx = 0;
+        }
+
+        let pairs = loader.order[self.idx..self.idx + batch_size].iter().map(|&i| &loader.pairs[i]);
+        let (sources, (inputs, targets)): (Vec<_>, (Vec<_>, Vec<_>)) = pairs
+            .map(|(source, target)| {
+                let input = [&[loader.eos_id], &target[..]].concat();
+                let target = [&target[..], &[loader.eos_id]].concat();
+                (source.clone(), (input, target))
+            })
+            .unzip();
+        self.idx += batch_size;
+
+        let (source, source_mask) = pad_batch(&sources, loader.pad_id);
+        let (input, _) = pad_batch(&inputs, loader.pad_id);
+        let (target, _) = pad_batch(&targets, loader.pad_id);
+        Some(PairBatch {
+            source,
+            source_mask,
+            input,
+            target,
+        })
+    }
+}
+
+// Rejects the zero sizes that would leave `len` dividing by zero or every window empty.
+fn check_sizes(batch_size: usize, seq_len_name: &str, seq_len: usize) -> Result<()> {
+    if batch_size == 0 || seq_len == 0 {
+        return Err(Error::InvalidConfig(format!(
+            "batch_size and {} must be positive, got {} and {}",
+            seq_len_name, batch_size, seq_len
+        )));
+    }
+    Ok(())
+}
+
+/// Right-pads variable-length `sequences` with `pad_id` to the longest one, returning the
+/// `[batch][seq]` ids and the matching attention mask (`true` for real tokens) for `Model::forward`.
+pub fn pad_batch(sequences: &[Vec<usize>], pad_id: usize) -> (Vec<Vec<usize>>, Vec<Vec<bool>>) {
+    let seq_len = sequences.iter().map(Vec::len).max().unwrap_or(0);
+    sequences
+        .iter()
+        .map(|seq| {
+            let mut ids = seq.clone();
+            ids.resize(seq_len, pad_id);
+            let mask = (0..seq_len).map(|i| i < seq.len()).collect();
+            (ids, mask)
+        })
+        .unzip()
+}
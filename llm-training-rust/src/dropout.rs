@@ -0,0 +1,70 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::float::Float;
+use crate::module::Module;
+use crate::parameter::Parameter;
+use crate::rng;
+use crate::tensor::Tensor;
+
+/// Inverted dropout: in training mode each element is zeroed with probability `rate` and the
+/// survivors are scaled by `1 / (1 - rate)`, so evaluation mode is the identity.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Dropout<T: Float = f32> {
+    rate: f64,
+    training: bool,
+    /// The scaled keep-mask applied by the last training-mode forward pass.
+    #[serde(skip)]
+    pub mask: Option<Tensor<T>>,
+}
+
+impl<T: Float> Dropout<T> {
+    /// Panics unless `0 <= rate < 1`, which `Config::validate` checks for `dropout_rate`.
+    pub fn new(rate: f64) -> Self {
+        assert!((0.0..1.0).contains(&rate), "dropout rate must be in [0, 1), got {}", rate);
+        Self {
+            rate,
+            training: true,
+            mask: None,
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    pub fn forward(&mut self, input: &Var<T>) -> Var<T> {
+        if !self.training || self.rate == 0.0 {
+            self.mask = None;
+            return input.clone();
+        }
+
+        let keep = 1.0 - self.rate;
+        let scale = T::from_f64(1.0 / keep);
+        let mask = rng::with_rng(|rng| {
+            Tensor::zeros(&input.shape()).map(|_| if rng.gen_range(0.0..1.0) < keep { scale } else { T::zero() })
+        });
+        self.mask = Some(mask.clone());
+        input.mul(&Var::constant(mask))
+    }
+}
+
+impl<T: Float> Module<T> for Dropout<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        Vec::new()
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        Vec::new()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
@@ -1,41 +1,42 @@
-pub struct Embedding {
-    embedding_matrix: Vec<Vec<f64>>,
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::module::{own, Module};
+use crate::parameter::Parameter;
+use crate::init::Init;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Embedding<T: Float = f32> {
+    embedding_matrix: Parameter<T>,
 }
 
-impl Embedding {
+impl<T: Float> Embedding<T> {
     pub fn new(vocab_size: usize, embedding_dim: usize) -> Self {
-        let mut embedding_matrix = Vec::with_capacity(vocab_size);
-        for _ in 0..vocab_size {
-            let embedding = Self::truncated_normal(embedding_dim);
-            embedding_matrix.push(embedding);
-        }
+        let init = Init::TruncatedNormal(1.0).tensor(&[vocab_size, embedding_dim], vocab_size, embedding_dim);
+        let embedding_matrix = Parameter::new("embedding_matrix", init);
 
         Self { embedding_matrix }
     }
 
-    pub fn forward(&self, input: &[usize]) -> Vec<Vec<f64>> {
-        input.iter().map(|&idx| self.embedding_matrix[idx].clone()).collect()
+    /// Looks up one row per token id, giving `[input.len(), embedding_dim]`.
+    pub fn forward(&self, input: &[usize]) -> Result<Var<T>> {
+        let vocab_size = self.embedding_matrix.shape()[0];
+        if let Some(&token_id) = input.iter().find(|&&id| id >= vocab_size) {
+            return Err(Error::VocabMismatch { vocab_size, token_id });
+        }
+        Ok(self.embedding_matrix.var().select_rows(input))
     }
+}
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>], input: &[usize]) {
-        for (idx, grad) in input.iter().zip(grad_output) {
-            for (embedding_val, grad_val) in self.embedding_matrix[*idx].iter_mut().zip(grad) {
-                *embedding_val += grad_val;
-            }
-        }
+impl<T: Float> Module<T> for Embedding<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        vec![own(&self.embedding_matrix)]
     }
 
-    fn truncated_normal(dim: usize) -> Vec<f64> {
-        let mut rng = rand::thread_rng();
-        let normal_dist = rand::distributions::Normal::new(0.0, 1.0);
-        let mut embedding = Vec::with_capacity(dim);
-        for _ in 0..dim {
-            let mut val = normal_dist.sample(&mut rng);
-            while val < -2.0 || val > 2.0 {
-                val = normal_dist.sample(&mut rng);
-            }
-            embedding.push(val);
-        }
-        embedding
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        vec![own(&mut self.embedding_matrix)]
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::transformer::Transformer;
+use crate::embedding::Embedding;
+use crate::positional_encoding::{PositionEncoding, PositionTable};
+use crate::layer_norm::LayerNorm;
+use crate::linear::Linear;
+use crate::optimizer::AdamOptimizer;
+use crate::data_loader::PairDataLoader;
+use crate::tokenizer::Tokenizer;
+use crate::generation;
+use crate::utils;
+use crate::attention::padding_mask;
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::model::{check_batch, cross_entropy_loss, embed_tokens};
+use crate::module::{prefixed, Module};
+use crate::parameter::Parameter;
+use crate::rng;
+
+/// A sequence-to-sequence transformer for tasks such as translation and summarization.
+///
+/// A bidirectional encoder (`causal` off) reads the source, and a causal decoder predicts the
+/// target while every decoder layer cross-attends over the encoder output. Source and target
+/// tokens share one embedding table and positional encoding; `Config::num_layers` sets the depth
+/// of both stacks.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EncoderDecoderModel<T: Float = f32> {
+    embedding: Embedding<T>,
+    /// Recorded so a checkpoint knows how it encodes positions.
+    position_encoding: PositionEncoding,
+    /// Absent with rotary embeddings and ALiBi, which encode positions inside attention instead.
+    position_table: Option<PositionTable<T>>,
+    max_seq_len: usize,
+    encoder: Transformer<T>,
+    decoder: Transformer<T>,
+    layer_norm: LayerNorm<T>,
+    linear: Linear<T>,
+    /// Target id excluded from the loss; see `Model::ignore_index`.
+    pub ignore_index: Option<usize>,
+}
+
+impl<T: Float> EncoderDecoderModel<T> {
+    /// Builds a freshly initialized model, reseeding the thread's random stream from
+    /// `config.seed` like `Model::new`. The decoder is causal regardless of `config.causal`.
+    pub fn new(config: &Config) -> Self {
+        rng::manual_seed(config.seed);
+        let embedding = Embedding::new(config.vocab_size, config.embedding_dim);
+        let position_table = PositionTable::for_config(config);
+        let encoder = Transformer::new(&Config {
+            causal: false,
+            ..config.clone()
+        });
+        let decoder = Transformer::decoder(&Config {
+            causal: true,
+            ..config.clone()
+        });
+        let layer_norm = LayerNorm::new(config.embedding_dim);
+        let linear = Linear::new(config.embedding_dim, config.vocab_size);
+
+        Self {
+            embedding,
+            position_encoding: config.position_encoding,
+            position_table,
+            max_seq_len: config.max_seq_len,
+            encoder,
+            decoder,
+            layer_norm,
+            linear,
+            ignore_index: None,
+        }
+    }
+
+    /// Encodes `[batch][source_len]` token ids into the `[batch, source_len, embedding_dim]`
+    /// memory the decoder attends over. `source_mask` marks real tokens `true` and padding
+    /// `false`, as in `Model::forward`.
+    pub fn encode(&mut self, source: &[Vec<usize>], source_mask: Option<&[Vec<bool>]>) -> Result<Var<T>> {
+        let batch_size = source.len();
+        let seq_len = source.first().map_or(0, |s| s.len());
+        check_batch("Model source", source, batch_size, seq_len)?;
+        if let Some(source_mask) = source_mask {
+            check_batch("Model source_mask", source_mask, batch_size, seq_len)?;
+        }
+        let mask = source_mask.map(padding_mask);
+
+        let embeddings = self.embed(source, 0)?;
+        self.encoder.forward(&embeddings, mask.as_ref())
+    }
+
+    /// Encodes `source` and decodes the `[batch][target_len]` decoder `input`, returning
+    /// `[batch, target_len, vocab_size]` logits and, given targets, the mean cross-entropy loss
+    /// over targets other than `ignore_index`. `PairDataLoader` yields all four arguments.
+    ///
+    /// The decoder is causal, so padding at the end of `input` never reaches real positions and
+    /// needs no mask; pad `target` with `ignore_index` instead.
+    pub fn forward(
+        &mut self,
+        source: &[Vec<usize>],
+        input: &[Vec<usize>],
+        target: Option<&[Vec<usize>]>,
+        source_mask: Option<&[Vec<bool>]>,
+    ) -> Result<(Var<T>, Option<Var<T>>)> {
+        let memory = self.encode(source, source_mask)?;
+        let batch_size = source.len();
+        let seq_len = input.first().map_or(0, |s| s.len());
+        check_batch("Model input", input, batch_size, seq_len)?;
+        if let Some(target) = target {
+            check_batch("Model target", target, batch_size, seq_len)?;
+        }
+        let memory_mask = source_mask.map(padding_mask);
+
+        let embeddings = self.embed(input, 0)?;
+        let decoder_output = self.decoder.forward_with_memory(&embeddings, None, &memory, memory_mask.as_ref())?;
+        let logits = self.project(&decoder_output)?;
+
+        let loss = match target {
+            Some(target) => Some(cross_entropy_loss(&logits, target, self.ignore_index)?),
+            None => None,
+        };
+        Ok((logits, loss))
+    }
+
+    /// Feeds the next `[batch][seq]` decoder tokens against the `memory` returned by `encode`,
+    /// like `Model::forward_step`. `memory` and `source_mask` must stay the same until
+    /// `clear_cache`.
+    pub fn forward_step(&mut self, input: &[Vec<usize>], memory: &Var<T>, source_mask: Option<&[Vec<bool>]>) -> Result<Var<T>> {
+        let batch_size = input.len();
+        let seq_len = input.first().map_or(0, |s| s.len());
+        check_batch("Model input", input, batch_size, seq_len)?;
+        let memory_mask = source_mask.map(padding_mask);
+
+        let embeddings = self.embed(input, self.decoder.position())?;
+        let decoder_output = self.decoder.forward_step_with_memory(&embeddings, memory, memory_mask.as_ref())?;
+        self.project(&decoder_output)
+    }
+
+    /// The position of the next `forward_step` token in the decoder.
+    pub fn position(&self) -> usize {
+        self.decoder.position()
+    }
+
+    /// Empties the decoder's KV caches, including the cached encoder output.
+    pub fn clear_cache(&mut self) {
+        self.decoder.clear_cache();
+    }
+
+    /// The position encoding the model was built with.
+    pub fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding
+    }
+
+    /// The longest source or target sequence the model accepts.
+    pub fn max_seq_len(&self) -> usize {
+        self.max_seq_len
+    }
+
+    pub fn encoder(&self) -> &Transformer<T> {
+        &self.encoder
+    }
+
+    pub fn decoder(&self) -> &Transformer<T> {
+        &self.decoder
+    }
+
+    fn embed(&self, input: &[Vec<usize>], offset: usize) -> Result<Var<T>> {
+        embed_tokens(&self.embedding, self.position_table.as_ref(), self.max_seq_len, input, offset)
+    }
+
+    // The final layer norm and vocabulary projection.
+    fn project(&mut self, decoder_output: &Var<T>) -> Result<Var<T>> {
+        let normed_output = self.layer_norm.forward(decoder_output)?;
+        self.linear.forward(&normed_output)
+    }
+
+    /// Trains on `data_loader`'s batches, setting `ignore_index` to its `<pad>` id first so the
+    /// padding of shorter targets does not count towards the loss. A loader with fewer pairs than
+    /// one batch is an `Error::InvalidConfig`.
+    pub fn fit(&mut self, data_loader: &mut PairDataLoader, config: &Config) -> Result<()> {
+        if data_loader.is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "batch_size ({}) needs at least that many sentence pairs, the data has {}",
+                data_loader.batch_size,
+                data_loader.num_pairs()
+            )));
+        }
+        self.ignore_index = Some(data_loader.pad_id());
+        let mut optimizer = AdamOptimizer::new(config.learning_rate);
+        let num_batches = data_loader.len();
+        self.train();
+
+        for epoch in 0..config.num_epochs {
+            let mut total_loss = 0.0;
+
+            for batch in data_loader.iter().take(num_batches) {
+                self.zero_grad();
+                let (_, loss) = self.forward(&batch.source, &batch.input, Some(&batch.target), Some(&batch.source_mask))?;
+                let loss = loss.expect("loss is computed when targets are given");
+                loss.backward();
+
+                optimizer.step(self);
+                total_loss += loss.value().item().to_f64();
+            }
+
+            let avg_loss = total_loss / num_batches as f64;
+            println!("Epoch: {}, Loss: {}", epoch + 1, avg_loss);
+
+            if (epoch + 1) % config.checkpoint_interval == 0 {
+                self.save_checkpoint(&format!("checkpoint_epoch_{}.pt", epoch + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples a target for `source`, up to `config.max_new_tokens` tokens (default
+    /// `max_seq_len`).
+    pub fn generate(&mut self, source: &str, tokenizer: &Tokenizer, config: &Config) -> Result<String> {
+        let max_new_tokens = config.max_new_tokens.unwrap_or(config.max_seq_len);
+        generation::generate_from_source(self, source, tokenizer, max_new_tokens)
+    }
+
+    pub fn save_checkpoint(&self, path: &str) -> Result<()> {
+        utils::save_checkpoint(self, path)
+    }
+
+    pub fn load_checkpoint(path: &str) -> Result<Self> {
+        utils::load_checkpoint(path)
+    }
+}
+
+impl<T: Float> Module<T> for EncoderDecoderModel<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("embedding", self.embedding.named_parameters()));
+        if let Some(position_table) = &self.position_table {
+            params.extend(prefixed("position_table", position_table.named_parameters()));
+        }
+        params.extend(prefixed("encoder", self.encoder.named_parameters()));
+        params.extend(prefixed("decoder", self.decoder.named_parameters()));
+        params.extend(prefixed("layer_norm", self.layer_norm.named_parameters()));
+        params.extend(prefixed("linear", self.linear.named_parameters()));
+        params
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("embedding", self.embedding.named_parameters_mut()));
+        if let Some(position_table) = &mut self.position_table {
+            params.extend(prefixed("position_table", position_table.named_parameters_mut()));
+        }
+        params.extend(prefixed("encoder", self.encoder.named_parameters_mut()));
+        params.extend(prefixed("decoder", self.decoder.named_parameters_mut()));
+        params.extend(prefixed("layer_norm", self.layer_norm.named_parameters_mut()));
+        params.extend(prefixed("linear", self.linear.named_parameters_mut()));
+        params
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.encoder.set_training(training);
+        self.decoder.set_training(training);
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// Everything that can go wrong when loading data, configs or checkpoints, or when a layer is fed
+/// input of the wrong shape.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing `path` failed.
+    Io { path: String, source: std::io::Error },
+    /// `path` was read but its contents are not valid for `what` (e.g. config JSON).
+    Parse { path: String, what: String, message: String },
+    /// `layer` received an input whose shape does not fit it.
+    ShapeMismatch { layer: String, expected: String, actual: Vec<usize> },
+    /// A token id, or a tokenizer's id range, falls outside the model's vocabulary.
+    VocabMismatch { vocab_size: usize, token_id: usize },
+    /// A checkpoint could not be decoded or does not match the model it is loaded into.
+    Checkpoint { path: String, message: String },
+    /// `Config::validate` rejected a setting or combination of settings.
+    InvalidConfig(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn io(path: &str, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub(crate) fn shape(layer: &str, expected: impl Into<String>, actual: &[usize]) -> Self {
+        Error::ShapeMismatch {
+            layer: layer.to_string(),
+            expected: expected.into(),
+            actual: actual.to_vec(),
+        }
+    }
+
+    /// Checks that `shape` has `ndim` axes (if given) and ends in `dim`.
+    pub(crate) fn check_last_dim(layer: &str, shape: &[usize], ndim: Option<usize>, dim: usize) -> Result<()> {
+        let ndim_ok = ndim.is_none_or(|n| shape.len() == n);
+        if ndim_ok && shape.last() == Some(&dim) {
+            return Ok(());
+        }
+        let expected = match ndim {
+            Some(n) => format!("[{}{}]", "_, ".repeat(n - 1), dim),
+            None => format!("[.., {}]", dim),
+        };
+        Err(Error::shape(layer, expected, shape))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Parse { path, what, message } => write!(f, "{}: invalid {}: {}", path, what, message),
+            Error::ShapeMismatch { layer, expected, actual } => {
+                write!(f, "{}: expected input of shape {}, got {:?}", layer, expected, actual)
+            }
+            Error::VocabMismatch { vocab_size, token_id } => {
+                write!(f, "token id {} is outside the model vocabulary of {} tokens", token_id, vocab_size)
+            }
+            Error::Checkpoint { path, message } => write!(f, "{}: bad checkpoint: {}", path, message),
+            Error::InvalidConfig(message) => write!(f, "invalid config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
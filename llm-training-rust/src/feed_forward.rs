@@ -1,40 +1,130 @@
+use serde::{Deserialize, Serialize};
+
 use crate::config::Config;
 use crate::linear::Linear;
-use crate::gelu::gelu;
+use crate::dropout::Dropout;
+use crate::activation::Activation;
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::module::{prefixed, Module};
+use crate::parameter::Parameter;
 
-pub struct FeedForward {
-    linear1: Linear,
-    linear2: Linear,
-    dropout: Dropout,
+/// The shape of the feed-forward block, chosen by `Config::ffn_type`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FfnType {
+    /// `linear2(act(linear1(x)))`.
+    #[default]
+    Mlp,
+    /// `linear2(act(gate(x)) * linear1(x))` with SiLU by default.
+    SwiGlu,
+    /// `linear2(act(gate(x)) * linear1(x))` with GELU by default.
+    GeGlu,
 }
 
-impl FeedForward {
+impl FfnType {
+    /// The activation used when `Config::activation` is unset.
+    pub fn default_activation(&self) -> Activation {
+        match self {
+            FfnType::SwiGlu => Activation::Silu,
+            FfnType::Mlp | FfnType::GeGlu => Activation::GeluTanh,
+        }
+    }
+
+    pub fn is_gated(&self) -> bool {
+        *self != FfnType::Mlp
+    }
+
+    /// The hidden width for a `feed_forward_dim` budget. Gated variants have a third projection,
+    /// so they shrink the width to two thirds to keep the parameter count of the plain MLP.
+    pub fn hidden_dim(&self, feed_forward_dim: usize) -> usize {
+        if self.is_gated() {
+            (2 * feed_forward_dim / 3).max(1)
+        } else {
+            feed_forward_dim
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FeedForward<T: Float = f32> {
+    ffn_type: FfnType,
+    activation: Activation,
+    linear1: Linear<T>,
+    /// The gate projection of the gated variants, whose activation scales `linear1`'s output.
+    gate: Option<Linear<T>>,
+    linear2: Linear<T>,
+    dropout: Dropout<T>,
+}
+
+impl<T: Float> FeedForward<T> {
     pub fn new(config: &Config) -> Self {
-        let linear1 = Linear::new(config.embedding_dim, config.feed_forward_dim);
-        let linear2 = Linear::new(config.feed_forward_dim, config.embedding_dim);
+        let ffn_type = config.ffn_type;
+        let activation = config.feed_forward_activation();
+        let hidden_dim = ffn_type.hidden_dim(config.feed_forward_dim);
+        let linear1 = Linear::new(config.embedding_dim, hidden_dim);
+        let gate = ffn_type.is_gated().then(|| Linear::new(config.embedding_dim, hidden_dim));
+        let linear2 = Linear::new(hidden_dim, config.embedding_dim);
         let dropout = Dropout::new(config.dropout_rate);
         Self {
+            ffn_type,
+            activation,
             linear1,
+            gate,
             linear2,
             dropout,
         }
     }
 
-    pub fn forward(&mut self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let mut hidden = self.linear1.forward(input);
-        hidden = hidden.iter().map(|h| h.iter().map(|&x| gelu(x)).collect()).collect();
-        let output = self.linear2.forward(&hidden);
-        self.dropout.forward(&output)
+    pub fn ffn_type(&self) -> FfnType {
+        self.ffn_type
+    }
+
+    pub fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    /// The width of the hidden layer.
+    pub fn hidden_dim(&self) -> usize {
+        self.linear1.output_size
+    }
+
+    pub fn forward(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        Error::check_last_dim("FeedForward", &input.shape(), None, self.linear1.input_size)?;
+        let up = self.linear1.forward(input)?;
+        let hidden = match &mut self.gate {
+            None => self.activation.forward(&up),
+            Some(gate) => self.activation.forward(&gate.forward(input)?).mul(&up),
+        };
+        let output = self.linear2.forward(&hidden)?;
+        Ok(self.dropout.forward(&output))
+    }
+}
+
+impl<T: Float> Module<T> for FeedForward<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("linear1", self.linear1.named_parameters()));
+        if let Some(gate) = &self.gate {
+            params.extend(prefixed("gate", gate.named_parameters()));
+        }
+        params.extend(prefixed("linear2", self.linear2.named_parameters()));
+        params
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("linear1", self.linear1.named_parameters_mut()));
+        if let Some(gate) = &mut self.gate {
+            params.extend(prefixed("gate", gate.named_parameters_mut()));
+        }
+        params.extend(prefixed("linear2", self.linear2.named_parameters_mut()));
+        params
     }
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let grad_dropout = self.dropout.backward(grad_output);
-        let grad_linear2 = self.linear2.backward(&grad_dropout);
-        let grad_gelu = grad_linear2
-            .iter()
-            .zip(self.linear1.output.iter())
-            .map(|(go, o)| go.iter().zip(o.iter()).map(|(&go_i, &o_i)| go_i * (1.0 - o_i.tanh().powi(2))).collect())
-            .collect();
-        self.linear1.backward(&grad_gelu)
+    fn set_training(&mut self, training: bool) {
+        self.dropout.set_training(training);
     }
-}
\ No newline at end of file
+}
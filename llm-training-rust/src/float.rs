@@ -0,0 +1,265 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The scalar type stored in tensors, parameters and optimizer state.
+///
+/// Hyperparameters such as learning rates and dropout rates stay `f64` and are converted at the
+/// point of use.
+pub trait Float:
+    Copy
+    + Default
+    + PartialOrd
+    + fmt::Debug
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + Serialize
+    + DeserializeOwned
+    + 'static
+{
+    /// The type long reductions such as matmul inner products accumulate in.
+    type Accum: Float;
+
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn to_accum(self) -> Self::Accum;
+    fn from_accum(x: Self::Accum) -> Self;
+
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn tanh(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn abs(self) -> Self;
+    fn max(self, other: Self) -> Self;
+
+    fn zero() -> Self {
+        Self::from_f64(0.0)
+    }
+
+    fn one() -> Self {
+        Self::from_f64(1.0)
+    }
+
+    fn neg_infinity() -> Self {
+        Self::from_f64(f64::NEG_INFINITY)
+    }
+}
+
+macro_rules! impl_native_float {
+    ($t:ty) => {
+        impl Float for $t {
+            type Accum = $t;
+
+            fn from_f64(x: f64) -> Self {
+                x as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn to_accum(self) -> Self {
+                self
+            }
+
+            fn from_accum(x: Self) -> Self {
+                x
+            }
+
+            fn exp(self) -> Self {
+                <$t>::exp(self)
+            }
+
+            fn ln(self) -> Self {
+                <$t>::ln(self)
+            }
+
+            fn sqrt(self) -> Self {
+                <$t>::sqrt(self)
+            }
+
+            fn tanh(self) -> Self {
+                <$t>::tanh(self)
+            }
+
+            fn powi(self, n: i32) -> Self {
+                <$t>::powi(self, n)
+            }
+
+            fn abs(self) -> Self {
+                <$t>::abs(self)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$t>::max(self, other)
+            }
+        }
+    };
+}
+
+impl_native_float!(f32);
+impl_native_float!(f64);
+
+/// A bfloat16 value: the top 16 bits of an `f32`, so it keeps the `f32` exponent range with an
+/// 8-bit mantissa.
+///
+/// Arithmetic is emulated by widening to `f32` and rounding the result back to nearest-even, which
+/// halves storage relative to `f32` but is not faster. Matmuls and sums accumulate in `f32`.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Bf16(u16);
+
+impl Bf16 {
+    pub fn from_bits(bits: u16) -> Self {
+        Bf16(bits)
+    }
+
+    pub fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        if x.is_nan() {
+            // Keep the sign and force a quiet NaN, which truncation alone could turn into infinity.
+            return Bf16((bits >> 16) as u16 | 0x0040);
+        }
+        let rounding = 0x7fff + ((bits >> 16) & 1);
+        Bf16((bits.wrapping_add(rounding) >> 16) as u16)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        f32::from_bits((self.0 as u32) << 16)
+    }
+}
+
+impl PartialEq for Bf16 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl PartialOrd for Bf16 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for Bf16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl fmt::Display for Bf16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+macro_rules! impl_bf16_op {
+    ($op:ident, $method:ident, $assign:ident, $assign_method:ident) => {
+        impl $op for Bf16 {
+            type Output = Bf16;
+
+            fn $method(self, rhs: Bf16) -> Bf16 {
+                Bf16::from_f32(self.to_f32().$method(rhs.to_f32()))
+            }
+        }
+
+        impl $assign for Bf16 {
+            fn $assign_method(&mut self, rhs: Bf16) {
+                *self = self.$method(rhs);
+            }
+        }
+    };
+}
+
+impl_bf16_op!(Add, add, AddAssign, add_assign);
+impl_bf16_op!(Sub, sub, SubAssign, sub_assign);
+impl_bf16_op!(Mul, mul, MulAssign, mul_assign);
+impl_bf16_op!(Div, div, DivAssign, div_assign);
+
+impl Neg for Bf16 {
+    type Output = Bf16;
+
+    fn neg(self) -> Bf16 {
+        Bf16(self.0 ^ 0x8000)
+    }
+}
+
+impl Sum for Bf16 {
+    fn sum<I: Iterator<Item = Bf16>>(iter: I) -> Bf16 {
+        Bf16::from_f32(iter.map(Bf16::to_f32).sum())
+    }
+}
+
+impl Float for Bf16 {
+    type Accum = f32;
+
+    fn from_f64(x: f64) -> Self {
+        Bf16::from_f32(x as f32)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.to_f32() as f64
+    }
+
+    fn to_accum(self) -> f32 {
+        self.to_f32()
+    }
+
+    fn from_accum(x: f32) -> Self {
+        Bf16::from_f32(x)
+    }
+
+    fn exp(self) -> Self {
+        Bf16::from_f32(self.to_f32().exp())
+    }
+
+    fn ln(self) -> Self {
+        Bf16::from_f32(self.to_f32().ln())
+    }
+
+    fn sqrt(self) -> Self {
+        Bf16::from_f32(self.to_f32().sqrt())
+    }
+
+    fn tanh(self) -> Self {
+        Bf16::from_f32(self.to_f32().tanh())
+    }
+
+    fn powi(self, n: i32) -> Self {
+        Bf16::from_f32(self.to_f32().powi(n))
+    }
+
+    fn abs(self) -> Self {
+        Bf16(self.0 & 0x7fff)
+    }
+
+    fn max(self, other: Self) -> Self {
+        Bf16::from_f32(self.to_f32().max(other.to_f32()))
+    }
+}
+
+/// Which `Float` a model is built with, chosen at runtime from `Config::dtype`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DType {
+    #[default]
+    F32,
+    F64,
+    Bf16,
+}
@@ -1,10 +1,54 @@
-pub fn gelu(x: f64) -> f64 {
-    0.5 * x * (1.0 + ((x / (2.0_f64.sqrt())).tanh()))
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_2_SQRT_PI};
+
+use crate::float::Float;
+
+const SQRT_2_OVER_PI: f64 = 0.7978845608028654;
+const COEFF: f64 = 0.044715;
+
+/// The tanh approximation of GELU, `0.5 x (1 + tanh(sqrt(2 / pi) (x + 0.044715 x^3)))`.
+pub fn gelu<T: Float>(x: T) -> T {
+    let x = x.to_f64();
+    let inner = SQRT_2_OVER_PI * (x + COEFF * x.powi(3));
+    T::from_f64(0.5 * x * (1.0 + inner.tanh()))
+}
+
+/// The derivative of `gelu` at `input`, multiplied by `grad_output`.
+pub fn gelu_backward<T: Float>(grad_output: T, input: T) -> T {
+    let x = input.to_f64();
+    let tanh = (SQRT_2_OVER_PI * (x + COEFF * x.powi(3))).tanh();
+    let d_inner = SQRT_2_OVER_PI * (1.0 + 3.0 * COEFF * x.powi(2));
+    grad_output * T::from_f64(0.5 * (1.0 + tanh) + 0.5 * x * (1.0 - tanh * tanh) * d_inner)
 }
 
-pub fn gelu_backward(grad_output: f64, input: f64) -> f64 {
-    let sqrt_2_over_pi = 0.7978845608028654;
-    let cdf = 0.5 * (1.0 + ((input * sqrt_2_over_pi).tanh()));
-    let pdf = (-0.5 * input.powi(2)).exp() / (2.0 * std::f64::consts::PI).sqrt();
-    grad_output * (cdf + input * pdf)
-}
\ No newline at end of file
+/// Exact GELU, `x * Phi(x) = 0.5 x (1 + erf(x / sqrt(2)))`.
+pub fn gelu_erf<T: Float>(x: T) -> T {
+    let x = x.to_f64();
+    T::from_f64(0.5 * x * (1.0 + erf(x * FRAC_1_SQRT_2)))
+}
+
+/// The derivative of `gelu_erf` at `input`, `Phi(x) + x * phi(x)`, multiplied by `grad_output`.
+pub fn gelu_erf_backward<T: Float>(grad_output: T, input: T) -> T {
+    let x = input.to_f64();
+    let cdf = 0.5 * (1.0 + erf(x * FRAC_1_SQRT_2));
+    let pdf = (-0.5 * x * x).exp() * FRAC_2_SQRT_PI * FRAC_1_SQRT_2 * 0.5;
+    grad_output * T::from_f64(cdf + x * pdf)
+}
+
+/// The error function, to within a few ulps.
+///
+/// Sums `erf(x) = 2 / sqrt(pi) * exp(-x^2) * sum_n (2 x^2)^n x / (2n + 1)!!`, whose terms are all
+/// positive, so nothing cancels. Past `|x| = 6` the result rounds to `±1`.
+pub fn erf(x: f64) -> f64 {
+    if x.abs() >= 6.0 {
+        return x.signum();
+    }
+    let two_x_sq = 2.0 * x * x;
+    let (mut term, mut sum) = (x, x);
+    let mut n = 0.0;
+    while term.abs() > f64::EPSILON * sum.abs() {
+        n += 1.0;
+        term *= two_x_sq / (2.0 * n + 1.0);
+        sum += term;
+    }
+    FRAC_2_SQRT_PI * (-x * x).exp() * sum
+}
@@ -0,0 +1,102 @@
+use rand::Rng;
+
+use crate::encoder_decoder::EncoderDecoderModel;
+use crate::error::Result;
+use crate::float::Float;
+use crate::model::Model;
+use crate::rng;
+use crate::module::Module;
+use crate::tensor::Tensor;
+use crate::tokenizer::Tokenizer;
+
+/// Samples up to `max_new_tokens` continuations of `prompt`, stopping early at end-of-sequence.
+/// The model is put in evaluation mode, so dropout is disabled. An empty prompt starts from
+/// `<eos>`, the start token `generate_from_source` uses too.
+///
+/// The prompt is fed once and every later step feeds only the newest token through the model's
+/// KV caches. When the positions reach `max_seq_len`, the caches are rebuilt from the most recent
+/// half of the context, so the model keeps seeing as much history as its positional encoding
+/// allows. A model in streaming mode (`Model::set_streaming`) never gets there: its caches stay
+/// at `num_sinks + window` positions, so `max_new_tokens` can be arbitrarily large.
+pub fn generate<T: Float>(model: &mut Model<T>, prompt: &str, tokenizer: &Tokenizer, max_new_tokens: usize) -> Result<String> {
+    let max_seq_len = model.max_seq_len();
+    let mut context = tokenizer.encode(prompt);
+    if context.is_empty() {
+        context.push(tokenizer.eos_id);
+    }
+    let mut pending = context[context.len().saturating_sub(max_seq_len)..].to_vec();
+    let mut generated_ids = Vec::new();
+    model.eval();
+    model.clear_cache();
+
+    for _ in 0..max_new_tokens {
+        if model.position() + pending.len() > max_seq_len {
+            model.clear_cache();
+            pending = context[context.len() - max_seq_len.div_ceil(2)..].to_vec();
+        }
+        let logits = model.forward_step(&[pending])?;
+        let next_id = sample_last(&logits.value());
+
+        if next_id == tokenizer.eos_id {
+            break;
+        }
+
+        generated_ids.push(next_id);
+        context.push(next_id);
+        pending = vec![next_id];
+    }
+
+    Ok(tokenizer.decode(&generated_ids))
+}
+
+/// Samples a translation of `source` from an encoder-decoder model, up to `max_new_tokens` tokens
+/// or `max_seq_len`, whichever is lower, stopping early at end-of-sequence.
+///
+/// The source is encoded once. The decoder starts from `<eos>`, as in training, and feeds one
+/// token per step through its KV caches; the encoder output's keys and values are cached by every
+/// cross-attention block on the first step.
+pub fn generate_from_source<T: Float>(
+    model: &mut EncoderDecoderModel<T>,
+    source: &str,
+    tokenizer: &Tokenizer,
+    max_new_tokens: usize,
+) -> Result<String> {
+    let mut source = tokenizer.encode(source);
+    source.truncate(model.max_seq_len());
+    let mut generated_ids = Vec::new();
+    model.eval();
+    model.clear_cache();
+
+    let memory = model.encode(&[source], None)?;
+    let mut next_id = tokenizer.eos_id;
+    for _ in 0..max_new_tokens.min(model.max_seq_len()) {
+        let logits = model.forward_step(&[vec![next_id]], &memory, None)?;
+        next_id = sample_last(&logits.value());
+
+        if next_id == tokenizer.eos_id {
+            break;
+        }
+        generated_ids.push(next_id);
+    }
+
+    Ok(tokenizer.decode(&generated_ids))
+}
+
+// Samples from the softmax of the last position of `[1, seq, vocab_size]` logits.
+fn sample_last<T: Float>(logits: &Tensor<T>) -> usize {
+    let seq_len = logits.dim(1);
+    let vocab_size = logits.dim(2);
+    let probs = logits.narrow(1, seq_len - 1, 1).reshape(&[vocab_size]).softmax(0);
+    let probs = probs.data().iter().map(|p| p.to_f64()).collect::<Vec<_>>();
+    sample_multinomial(&probs)
+}
+
+/// Draws an index with probability proportional to `probs`.
+pub fn sample_multinomial(probs: &[f64]) -> usize {
+    let mut cum_probs = probs.to_vec();
+    for i in 1..cum_probs.len() {
+        cum_probs[i] += cum_probs[i - 1];
+    }
+    let r: f64 = rng::with_rng(|rng| rng.gen_range(0.0..1.0)) * cum_probs[cum_probs.len() - 1];
+    cum_probs.iter().position(|&p| p > r).unwrap_or(cum_probs.len() - 1)
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use crate::autograd::Var;
+use crate::init::Init;
+use crate::module::Module;
+use crate::tensor::Tensor;
+
+/// Step size and tolerances for `gradcheck_with`.
+#[derive(Debug, Clone, Copy)]
+pub struct GradcheckOptions {
+    /// Half-width of the central difference.
+    pub eps: f64,
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl Default for GradcheckOptions {
+    fn default() -> Self {
+        Self {
+            eps: 1e-6,
+            atol: 1e-5,
+            rtol: 1e-3,
+        }
+    }
+}
+
+/// The first element whose analytical gradient disagrees with the finite-difference estimate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradcheckError {
+    /// `input.<i>` for the i-th checked input, otherwise the parameter's registered name.
+    pub name: String,
+    /// Flat (row-major) index into the tensor.
+    pub index: usize,
+    pub analytical: f64,
+    pub numerical: f64,
+}
+
+impl fmt::Display for GradcheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gradient mismatch for {}[{}]: analytical {}, numerical {}",
+            self.name, self.index, self.analytical, self.numerical
+        )
+    }
+}
+
+impl std::error::Error for GradcheckError {}
+
+/// `gradcheck_with` using the default options.
+pub fn gradcheck<M, F>(module: &mut M, inputs: &[&Var<f64>], forward: F) -> Result<(), GradcheckError>
+where
+    M: Module<f64>,
+    F: FnMut(&mut M) -> Var<f64>,
+{
+    gradcheck_with(&GradcheckOptions::default(), module, inputs, forward)
+}
+
+/// Compares the gradients from `backward` against central differences, for every element of
+/// every input in `inputs` and every parameter of `module`.
+///
+/// `forward` runs the module on the (captured) inputs; its output is reduced to a scalar by a dot
+/// product with fixed random weights, so errors cannot cancel out under a plain sum. Inputs must
+/// be `Var::leaf`s. The module is switched to evaluation mode so dropout is deterministic, and
+/// runs `2 * numel + 1` forward passes in total, so keep the shapes small.
+pub fn gradcheck_with<M, F>(
+    options: &GradcheckOptions,
+    module: &mut M,
+    inputs: &[&Var<f64>],
+    mut forward: F,
+) -> Result<(), GradcheckError>
+where
+    M: Module<f64>,
+    F: FnMut(&mut M) -> Var<f64>,
+{
+    module.eval();
+    let mut checked = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, &input)| (format!("input.{}", i), input.clone()))
+        .collect::<Vec<_>>();
+    checked.extend(module.named_parameters().into_iter().map(|(name, p)| (name, p.var().clone())));
+
+    for (_, var) in &checked {
+        var.zero_grad();
+    }
+    let output = forward(module);
+    let weights: Tensor<f64> = Init::Uniform(1.0).tensor(&output.shape(), 1, 1);
+    output.backward_with(weights.clone());
+
+    let mut objective = |module: &mut M| forward(module).value().mul(&weights).sum();
+
+    for (name, var) in &checked {
+        let analytical = var.grad().unwrap_or_else(|| Tensor::zeros(&var.shape())).contiguous();
+        for index in 0..analytical.numel() {
+            let original = var.value().data()[index];
+
+            var.value_mut().data_mut()[index] = original + options.eps;
+            let plus = objective(module);
+            var.value_mut().data_mut()[index] = original - options.eps;
+            let minus = objective(module);
+            var.value_mut().data_mut()[index] = original;
+
+            let numerical = (plus - minus) / (2.0 * options.eps);
+            let analytical = analytical.data()[index];
+            if (analytical - numerical).abs() > options.atol + options.rtol * numerical.abs() {
+                return Err(GradcheckError {
+                    name: name.clone(),
+                    index,
+                    analytical,
+                    numerical,
+                });
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::float::Float;
+use crate::rng;
+use crate::tensor::Tensor;
+
+/// How a weight tensor is filled at construction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Init {
+    Zeros,
+    Constant(f64),
+    /// Uniform on `[-bound, bound]`.
+    Uniform(f64),
+    /// Normal with mean zero and the given standard deviation.
+    Normal(f64),
+    /// Normal with the given standard deviation, resampled outside two standard deviations.
+    TruncatedNormal(f64),
+    /// Glorot/Xavier uniform, scaled by fan-in and fan-out.
+    XavierUniform,
+}
+
+impl Init {
+    pub fn tensor<T: Float>(&self, shape: &[usize], fan_in: usize, fan_out: usize) -> Tensor<T> {
+        let numel = shape.iter().product();
+        let data: Vec<f64> = rng::with_rng(|rng| match *self {
+            Init::Zeros => vec![0.0; numel],
+            Init::Constant(value) => vec![value; numel],
+            Init::Uniform(bound) => (0..numel).map(|_| rng.gen_range(-bound..=bound)).collect(),
+            Init::Normal(std) => (0..numel).map(|_| std * standard_normal(rng)).collect(),
+            Init::TruncatedNormal(std) => (0..numel)
+                .map(|_| loop {
+                    let val = standard_normal(rng);
+                    if (-2.0..=2.0).contains(&val) {
+                        break std * val;
+                    }
+                })
+                .collect(),
+            Init::XavierUniform => {
+                let bound = (6.0 / (fan_in + fan_out) as f64).sqrt();
+                (0..numel).map(|_| rng.gen_range(-bound..=bound)).collect()
+            }
+        });
+        Tensor::new(data.into_iter().map(T::from_f64).collect(), shape)
+    }
+}
+
+// Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen_range(0.0..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
@@ -1,85 +1,52 @@
-use std::ops::{AddAssign, SubAssign};
-
-pub struct LayerNorm {
-    gamma: Vec<f64>,
-    beta: Vec<f64>,
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::module::{own, Module};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LayerNorm<T: Float = f32> {
+    gamma: Parameter<T>,
+    beta: Parameter<T>,
     eps: f64,
 }
 
-impl LayerNorm {
+impl<T: Float> LayerNorm<T> {
     pub fn new(dim: usize) -> Self {
-        let gamma = vec![1.0; dim];
-        let beta = vec![0.0; dim];
+        let gamma = Parameter::new("gamma", Tensor::ones(&[dim]));
+        let beta = Parameter::new("beta", Tensor::zeros(&[dim]));
         let eps = 1e-5;
 
         Self { gamma, beta, eps }
     }
 
-    pub fn forward(&self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let mut output = Vec::with_capacity(input.len());
-        let dim = input[0].len();
-
-        for i in 0..input.len() {
-            let mean = input[i].iter().sum::<f64>() / dim as f64;
-            let variance = input[i].iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / dim as f64;
-            let std_dev = (variance + self.eps).sqrt();
+    /// Normalizes over the last axis of `input`.
+    pub fn forward(&self, input: &Var<T>) -> Result<Var<T>> {
+        let shape = input.shape();
+        Error::check_last_dim("LayerNorm", &shape, None, self.gamma.numel())?;
+        let axis = shape.len() - 1;
+        let eps = T::from_f64(self.eps);
+        let (two, half) = (T::from_f64(2.0), T::from_f64(0.5));
 
-            let normalized = input[i]
-                .iter()
-                .zip(&self.gamma)
-                .zip(&self.beta)
-                .map(|((&x, &g), &b)| (x - mean) / std_dev * g + b)
-                .collect();
+        let mean = input.mean_axis(axis, true);
+        let centered = input.sub(&mean);
+        let variance = centered.map(|x| x * x, move |x| two * x).mean_axis(axis, true);
+        let std_dev = variance.map(move |v| (v + eps).sqrt(), move |v| half / (v + eps).sqrt());
 
-            output.push(normalized);
-        }
-
-        output
+        Ok(centered.div(&std_dev).mul(self.gamma.var()).add(self.beta.var()))
     }
+}
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>], input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let mut grad_input = Vec::with_capacity(input.len());
-        let dim = input[0].len();
-
-        for i in 0..input.len() {
-            let mean = input[i].iter().sum::<f64>() / dim as f64;
-            let variance = input[i].iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / dim as f64;
-            let std_dev = (variance + self.eps).sqrt();
-
-            let mut d_gamma = vec![0.0; dim];
-            let mut d_beta = vec![0.0; dim];
-            let mut d_input = vec![0.0; dim];
-
-            for j in 0..dim {
-                let normalized = (input[i][j] - mean) / std_dev;
-                d_gamma[j] = grad_output[i][j] * normalized;
-                d_beta[j] = grad_output[i][j];
-            }
-
-            let mut d_norm = vec![0.0; dim];
-            for j in 0..dim {
-                d_norm[j] = grad_output[i][j] * self.gamma[j];
-            }
-
-            let d_variance = d_norm
-                .iter()
-                .zip(&input[i])
-                .map(|(&d, &x)| d * (x - mean) * -0.5 * (variance + self.eps).powf(-1.5))
-                .sum::<f64>();
-
-            let d_mean = d_norm.iter().sum::<f64>() * -1.0 / std_dev
-                + d_variance * input[i].iter().map(|&x| -2.0 * (x - mean)).sum::<f64>() / dim as f64;
-
-            for j in 0..dim {
-                d_input[j] = d_norm[j] / std_dev + d_variance * 2.0 * (input[i][j] - mean) / dim as f64 + d_mean / dim as f64;
-            }
-
-            self.gamma.iter_mut().zip(&d_gamma).for_each(|(g, d)| g.add_assign(d));
-            self.beta.iter_mut().zip(&d_beta).for_each(|(b, d)| b.add_assign(d));
-
-            grad_input.push(d_input);
-        }
+impl<T: Float> Module<T> for LayerNorm<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        vec![own(&self.gamma), own(&self.beta)]
+    }
 
-        grad_input
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        vec![own(&mut self.gamma), own(&mut self.beta)]
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,42 @@
+pub mod activation;
+pub mod alibi;
+pub mod attention;
+pub mod autograd;
+pub mod config;
+pub mod data_loader;
+pub mod dropout;
+pub mod embedding;
+pub mod encoder_decoder;
+pub mod error;
+pub mod feed_forward;
+pub mod float;
+pub mod gelu;
+pub mod generation;
+pub mod gradcheck;
+pub mod init;
+pub mod layer_norm;
+pub mod linear;
+pub mod model;
+pub mod module;
+pub mod optimizer;
+pub mod parameter;
+pub mod positional_encoding;
+pub mod rng;
+pub mod rotary;
+pub mod silu;
+pub mod tensor;
+pub mod tiled_attention;
+pub mod tokenizer;
+pub mod transformer;
+pub mod utils;
+
+pub use config::Config;
+pub use data_loader::{DataLoader, PairDataLoader};
+pub use encoder_decoder::EncoderDecoderModel;
+pub use error::{Error, Result};
+pub use float::{Bf16, DType, Float};
+pub use generation::generate;
+pub use model::Model;
+pub use module::Module;
+pub use optimizer::AdamOptimizer;
+pub use tokenizer::Tokenizer;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::init::Init;
+use crate::module::{own, Module};
+use crate::parameter::Parameter;
+
+/// `y = x W + b`, applied over the last axis of the input.
+///
+/// The matmul node keeps its input, so the backward pass needs no extra cache here.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Linear<T: Float = f32> {
+    pub input_size: usize,
+    pub output_size: usize,
+    weight: Parameter<T>,
+    bias: Option<Parameter<T>>,
+}
+
+impl<T: Float> Linear<T> {
+    pub fn new(input_size: usize, output_size: usize) -> Self {
+        Self::with_init(input_size, output_size, true, Init::XavierUniform)
+    }
+
+    pub fn with_init(input_size: usize, output_size: usize, bias: bool, init: Init) -> Self {
+        let weight = Parameter::new("weight", init.tensor(&[input_size, output_size], input_size, output_size));
+        let bias = bias.then(|| Parameter::new("bias", Init::Zeros.tensor(&[output_size], input_size, output_size)));
+
+        Self {
+            input_size,
+            output_size,
+            weight,
+            bias,
+        }
+    }
+
+    pub fn forward(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        Error::check_last_dim("Linear", &input.shape(), None, self.input_size)?;
+        let output = input.matmul(self.weight.var());
+        Ok(match &self.bias {
+            Some(bias) => output.add(bias.var()),
+            None => output,
+        })
+    }
+}
+
+impl<T: Float> Module<T> for Linear<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = vec![own(&self.weight)];
+        params.extend(self.bias.as_ref().map(own));
+        params
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = vec![own(&mut self.weight)];
+        params.extend(self.bias.as_mut().map(own));
+        params
+    }
+}
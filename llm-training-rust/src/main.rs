@@ -1,41 +1,47 @@
-use crate::config::Config;
-use crate::model::Model;
-use crate::data_loader::DataLoader;
-use crate::tokenizer::Tokenizer;
-
-mod config;
-mod model;
-mod attention;
-mod layer_norm;
-mod gelu;
-mod embedding;
-mod positional_encoding;
-mod feed_forward;
-mod transformer;
-mod optimizer;
-mod data_loader;
-mod tokenizer;
-mod utils;
+use llm_training_rust::{Bf16, Config, DType, DataLoader, Float, Model, Result, Tokenizer};
 
 fn main() {
+    if let Err(err) = try_main() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<()> {
     // Load the configuration
-    let config = Config::from_file("config.json");
+    let config = Config::from_file("config.json")?;
+    config.validate()?;
 
     // Initialize the tokenizer
-    let tokenizer = Tokenizer::new("vocab.txt");
+    let tokenizer = Tokenizer::new("vocab.txt")?;
 
     // Load the training data
-    let train_data = DataLoader::new("data/tiny_shakespeare_train.txt", config.batch_size, config.seq_len, &tokenizer);
-
-    // Initialize the model
-    let mut model = Model::new(&config);
+    let mut train_data = DataLoader::new("data/tiny_shakespeare_train.txt", config.batch_size, config.max_seq_len, &tokenizer)?;
+    train_data.shuffle = true;
+    config.validate_with(&tokenizer, &train_data)?;
+
+    // Build and train the model in the configured precision
+    match config.dtype {
+        DType::F32 => run::<f32>(&config, &tokenizer, &mut train_data),
+        DType::F64 => run::<f64>(&config, &tokenizer, &mut train_data),
+        DType::Bf16 => run::<Bf16>(&config, &tokenizer, &mut train_data),
+    }
+}
+
+fn run<T: Float>(config: &Config, tokenizer: &Tokenizer, train_data: &mut DataLoader) -> Result<()> {
+    // Initialize the model, or continue from a checkpoint at this config's max_seq_len
+    let mut model = match &config.init_checkpoint {
+        Some(path) => Model::<T>::from_checkpoint(path, config)?,
+        None => Model::<T>::new(config),
+    };
 
     // Train the model
-    model.train(&train_data, &config);
+    model.fit(train_data, config)?;
 
     // Generate text
     let prompt = "To be, or not to be";
-    let generated_text = model.generate(prompt, &tokenizer, &config);
+    let generated_text = model.generate(prompt, tokenizer, config)?;
 
     println!("Generated text: {}", generated_text);
-}
\ No newline at end of file
+    Ok(())
+}
@@ -1,161 +1,448 @@
+use serde::{Deserialize, Serialize};
+
 use crate::config::Config;
 use crate::transformer::Transformer;
 use crate::embedding::Embedding;
-use crate::positional_encoding::PositionalEncoding;
+use crate::positional_encoding::{PositionEncoding, PositionScaling, PositionTable, PositionalEncoding};
 use crate::layer_norm::LayerNorm;
+use crate::linear::Linear;
 use crate::optimizer::AdamOptimizer;
 use crate::data_loader::DataLoader;
 use crate::tokenizer::Tokenizer;
+use crate::generation;
+use crate::utils;
+use crate::attention::{padding_mask, Streaming};
+use crate::autograd::Var;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::module::{prefixed, Module};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+use crate::rng;
 
-pub struct Model {
-    embedding: Embedding,
-    positional_encoding: PositionalEncoding,
-    transformer: Transformer,
-    layer_norm: LayerNorm,
-    linear: Linear,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Model<T: Float = f32> {
+    embedding: Embedding<T>,
+    /// Recorded so a checkpoint knows how it encodes positions.
+    position_encoding: PositionEncoding,
+    /// Absent with rotary embeddings and ALiBi, which encode positions inside attention instead.
+    position_table: Option<PositionTable<T>>,
+    position_scaling: Option<PositionScaling>,
+    max_seq_len: usize,
+    transformer: Transformer<T>,
+    layer_norm: LayerNorm<T>,
+    linear: Linear<T>,
+    /// Target id excluded from the loss, normally the tokenizer's `<pad>` id. `Model::new` leaves
+    /// it unset, since the config does not know the tokenizer; `fit` sets it from its loader.
+    pub ignore_index: Option<usize>,
 }
 
-impl Model {
+impl<T: Float> Model<T> {
+    /// Builds a freshly initialized model. Reseeds the thread's random stream from `config.seed`
+    /// first, so the weights and every later random draw are reproducible.
+    ///
+    /// The layers assert on impossible sizes, so pass a config that `Config::validate` accepts.
     pub fn new(config: &Config) -> Self {
+        rng::manual_seed(config.seed);
         let embedding = Embedding::new(config.vocab_size, config.embedding_dim);
-        let positional_encoding = PositionalEncoding::new(config.max_seq_len, config.embedding_dim);
+        let position_table = PositionTable::for_config(config);
         let transformer = Transformer::new(config);
         let layer_norm = LayerNorm::new(config.embedding_dim);
         let linear = Linear::new(config.embedding_dim, config.vocab_size);
 
         Self {
             embedding,
-            positional_encoding,
+            position_encoding: config.position_encoding,
+            position_table,
+            position_scaling: config.position_scaling,
+            max_seq_len: config.max_seq_len,
             transformer,
             layer_norm,
             linear,
+            ignore_index: None,
         }
     }
 
-    pub fn forward(&self, input: &[usize], target: Option<&[usize]>) -> (Vec<Vec<f64>>, Option<f64>) {
-        let mut embeddings = self.embedding.forward(input);
-        let positional_encodings = self.positional_encoding.forward(input.len());
-
-        for (embedding, positional_encoding) in embeddings.iter_mut().zip(positional_encodings.iter()) {
-            for (e, p) in embedding.iter_mut().zip(positional_encoding.iter()) {
-                *e += p;
-            }
+    /// Takes `[batch][seq]` token ids and returns `[batch, seq, vocab_size]` logits and, given
+    /// targets, the mean cross-entropy loss over targets other than `ignore_index`.
+    ///
+    /// `attention_mask` marks real tokens `true` and padding `false`; padded positions are hidden
+    /// from attention. Pad their targets with `ignore_index` so they do not count towards the loss.
+    pub fn forward(
+        &mut self,
+        input: &[Vec<usize>],
+        target: Option<&[Vec<usize>]>,
+        attention_mask: Option<&[Vec<bool>]>,
+    ) -> Result<(Var<T>, Option<Var<T>>)> {
+        let batch_size = input.len();
+        let seq_len = input.first().map_or(0, |s| s.len());
+        check_batch("Model input", input, batch_size, seq_len)?;
+        if let Some(target) = target {
+            check_batch("Model target", target, batch_size, seq_len)?;
+        }
+        if let Some(attention_mask) = attention_mask {
+            check_batch("Model attention_mask", attention_mask, batch_size, seq_len)?;
         }
+        let mask = attention_mask.map(padding_mask);
 
-        let transformer_output = self.transformer.forward(&embeddings);
-        let normed_output = self.layer_norm.forward(&transformer_output);
-        let logits = self.linear.forward(&normed_output);
+        let embeddings = self.embed(input, 0)?;
+        let transformer_output = self.transformer.forward(&embeddings, mask.as_ref())?;
+        let normed_output = self.layer_norm.forward(&transformer_output)?;
+        let logits = self.linear.forward(&normed_output)?;
 
         let loss = match target {
             Some(target) => {
-                let loss = self.cross_entropy_loss(&logits, target);
+                let loss = self.cross_entropy_loss(&logits, target)?;
                 Some(loss)
             }
             None => None,
         };
 
-        (logits, loss)
+        Ok((logits, loss))
+    }
+
+    /// Feeds the next `[batch][seq]` tokens of sequences whose earlier tokens are in the KV caches
+    /// and returns their `[batch, seq, vocab_size]` logits. The first call after `clear_cache`
+    /// processes the prompt; later calls typically pass one token each.
+    ///
+    /// Positions run up to `max_seq_len`; going past that is an error, so callers clear the cache
+    /// and re-feed a shorter context instead.
+    pub fn forward_step(&mut self, input: &[Vec<usize>]) -> Result<Var<T>> {
+        let batch_size = input.len();
+        let seq_len = input.first().map_or(0, |s| s.len());
+        check_batch("Model input", input, batch_size, seq_len)?;
+
+        let embeddings = self.embed(input, self.transformer.position())?;
+        let transformer_output = self.transformer.forward_step(&embeddings)?;
+        let normed_output = self.layer_norm.forward(&transformer_output)?;
+        self.linear.forward(&normed_output)
     }
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>], input: &[usize], target: &[usize]) {
-        let grad_linear = self.linear.backward(grad_output);
-        let grad_layer_norm = self.layer_norm.backward(&grad_linear, &self.transformer.output);
-        let grad_transformer = self.transformer.backward(&grad_layer_norm);
+    /// The position of the next `forward_step` token: the number of positions fed since the last
+    /// `clear_cache`, or its index in the caches when streaming.
+    pub fn position(&self) -> usize {
+        self.transformer.position()
+    }
+
+    /// Empties the KV caches, so the next `forward_step` starts a new sequence.
+    pub fn clear_cache(&mut self) {
+        self.transformer.clear_cache();
+    }
 
-        let mut grad_embeddings = grad_transformer;
-        let grad_positional_encodings = self.positional_encoding.backward(&grad_embeddings);
+    /// The longest sequence the model accepts, which also bounds the KV caches.
+    pub fn max_seq_len(&self) -> usize {
+        self.max_seq_len
+    }
 
-        for (grad_embedding, grad_positional_encoding) in grad_embeddings.iter_mut().zip(grad_positional_encodings.iter()) {
-            for (ge, gp) in grad_embedding.iter_mut().zip(grad_positional_encoding.iter()) {
-                *ge += gp;
+    pub fn transformer(&self) -> &Transformer<T> {
+        &self.transformer
+    }
+
+    /// The position encoding the model was built with.
+    pub fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding
+    }
+
+    /// Changes the longest accepted sequence, e.g. to evaluate past the training length. The
+    /// sinusoidal table is rebuilt to cover it; rotary embeddings and ALiBi need no table. A
+    /// learned table grows by position interpolation (see `LearnedPositionalEmbedding::interpolate`).
+    ///
+    /// Sinusoidal and rotary positions past the training length are unseen angles; combine this
+    /// with `set_position_scaling` to keep them in the trained range.
+    pub fn set_max_seq_len(&mut self, max_seq_len: usize) {
+        match &mut self.position_table {
+            Some(PositionTable::Sinusoidal(table)) => {
+                let embedding_dim = table.encodings.dim(1);
+                *table = PositionalEncoding::with_scaling(max_seq_len, embedding_dim, self.position_scaling);
+            }
+            Some(PositionTable::Learned(table)) => table.interpolate(max_seq_len),
+            None => {}
+        }
+        self.max_seq_len = max_seq_len;
+        self.clear_cache();
+    }
+
+    pub fn position_scaling(&self) -> Option<PositionScaling> {
+        self.position_scaling
+    }
+
+    /// Rescales the sinusoidal table or the rotary frequencies in every layer, or restores them
+    /// with `None`, and clears the KV caches. The scaling is saved in checkpoints.
+    ///
+    /// Scaling a learned table or ALiBi, or a factor below 1, is an `Error::InvalidConfig`.
+    pub fn set_position_scaling(&mut self, scaling: Option<PositionScaling>) -> Result<()> {
+        if let Some(scaling) = scaling {
+            if !matches!(self.position_encoding, PositionEncoding::Sinusoidal | PositionEncoding::Rope) {
+                return Err(Error::InvalidConfig(format!(
+                    "position_scaling applies to sinusoidal and rope position encodings, the model uses {:?}",
+                    self.position_encoding
+                )));
             }
+            scaling.check()?;
         }
+        self.position_scaling = scaling;
+        if let Some(PositionTable::Sinusoidal(table)) = &mut self.position_table {
+            let embedding_dim = table.encodings.dim(1);
+            *table = PositionalEncoding::with_scaling(self.max_seq_len, embedding_dim, scaling);
+        }
+        self.transformer.set_position_scaling(scaling);
+        Ok(())
+    }
+
+    /// `exp` of the mean cross-entropy over the non-ignored targets, with dropout disabled. The
+    /// model is returned to its previous mode afterwards, so this can run mid-training.
+    pub fn perplexity(&mut self, input: &[Vec<usize>], target: &[Vec<usize>]) -> Result<f64> {
+        let training = self.is_training();
+        self.eval();
+        let loss = self.forward(input, Some(target), None);
+        self.set_training(training);
+        let loss = loss?.1.expect("loss is computed when targets are given");
+        Ok(loss.value().item().to_f64().exp())
+    }
+
+    /// Whether dropout is active; see `Module::train` and `Module::eval`.
+    pub fn is_training(&self) -> bool {
+        self.transformer.is_training()
+    }
 
-        self.embedding.backward(&grad_embeddings, input);
+    // Token plus positional embeddings for `input` starting at position `offset`,
+    // `[batch, seq, embedding_dim]`.
+    fn embed(&self, input: &[Vec<usize>], offset: usize) -> Result<Var<T>> {
+        embed_tokens(&self.embedding, self.position_table.as_ref(), self.max_seq_len, input, offset)
     }
 
-    pub fn train(&mut self, data_loader: &DataLoader, config: &Config) {
+    /// Trains on `data_loader`'s batches, setting `ignore_index` to its `<pad>` id first. A
+    /// loader with fewer windows than one batch is an `Error::InvalidConfig`.
+    pub fn fit(&mut self, data_loader: &mut DataLoader, config: &Config) -> Result<()> {
+        if data_loader.is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "batch_size * seq_len ({} * {}) needs more tokens than the data's {}",
+                data_loader.batch_size,
+                data_loader.seq_len,
+                data_loader.num_tokens()
+            )));
+        }
+        self.ignore_index = Some(data_loader.pad_id());
         let mut optimizer = AdamOptimizer::new(config.learning_rate);
+        let num_batches = data_loader.len();
+        self.train();
 
         for epoch in 0..config.num_epochs {
             let mut total_loss = 0.0;
 
-            for (batch_input, batch_target) in data_loader.iter() {
-                let (logits, loss) = self.forward(&batch_input, Some(&batch_target));
-                self.backward(&logits, &batch_input, &batch_target);
+            for (batch_input, batch_target) in data_loader.iter().take(num_batches) {
+                self.zero_grad();
+                let (_, loss) = self.forward(&batch_input, Some(&batch_target), None)?;
+                let loss = loss.expect("loss is computed when targets are given");
+                loss.backward();
 
                 optimizer.step(self);
-                total_loss += loss.unwrap();
+                total_loss += loss.value().item().to_f64();
             }
 
-            let avg_loss = total_loss / data_loader.len() as f64;
+            let avg_loss = total_loss / num_batches as f64;
             println!("Epoch: {}, Loss: {}", epoch + 1, avg_loss);
 
             if (epoch + 1) % config.checkpoint_interval == 0 {
-                self.save_checkpoint(&format!("checkpoint_epoch_{}.pt", epoch + 1));
+                self.save_checkpoint(&format!("checkpoint_epoch_{}.pt", epoch + 1))?;
             }
         }
+        Ok(())
     }
 
-    pub fn generate(&self, prompt: &str, tokenizer: &Tokenizer, config: &Config) -> String {
-        let mut input_ids = tokenizer.encode(prompt);
-        let mut generated_ids = Vec::new();
-
-        for _ in 0..config.max_seq_len {
-            let (logits, _) = self.forward(&input_ids, None);
-            let last_logits = logits.last().unwrap();
-            let probs = softmax(last_logits);
-            let next_id = sample_multinomial(&probs);
+    /// Samples `config.max_new_tokens` tokens (default `max_seq_len`), streaming with attention
+    /// sinks if `config.streaming` is set.
+    pub fn generate(&mut self, prompt: &str, tokenizer: &Tokenizer, config: &Config) -> Result<String> {
+        self.set_streaming(config.streaming)?;
+        let max_new_tokens = config.max_new_tokens.unwrap_or(config.max_seq_len);
+        generation::generate(self, prompt, tokenizer, max_new_tokens)
+    }
 
-            if next_id == tokenizer.eos_id {
-                break;
+    /// Switches `forward_step` to attention-sink streaming (see `Streaming`), or back with `None`,
+    /// and clears the KV caches. The sinks and window must fit within `max_seq_len`.
+    ///
+    /// Streaming renumbers the cached positions, which only works when positions are applied
+    /// inside attention: the model must use rotary embeddings or ALiBi. Sinusoidal and learned
+    /// vectors are baked into the cached keys and values when a token is fed.
+    pub fn set_streaming(&mut self, streaming: Option<Streaming>) -> Result<()> {
+        if let Some(s) = streaming {
+            if !matches!(self.position_encoding, PositionEncoding::Rope | PositionEncoding::Alibi) {
+                return Err(Error::InvalidConfig(format!(
+                    "streaming needs rope or alibi position encoding, the model uses {:?}",
+                    self.position_encoding
+                )));
+            }
+            if s.window == 0 || s.num_sinks + s.window > self.max_seq_len {
+                return Err(Error::InvalidConfig(format!(
+                    "streaming needs 0 < window and num_sinks + window <= max_seq_len, got {} + {} for {}",
+                    s.num_sinks, s.window, self.max_seq_len
+                )));
             }
-
-            generated_ids.push(next_id);
-            input_ids.push(next_id);
-            input_ids = input_ids.split_off(1);
         }
+        self.transformer.set_streaming(streaming);
+        Ok(())
+    }
 
-        let generated_text = tokenizer.decode(&generated_ids);
-        generated_text
+    fn cross_entropy_loss(&self, logits: &Var<T>, target: &[Vec<usize>]) -> Result<Var<T>> {
+        cross_entropy_loss(logits, target, self.ignore_index)
     }
 
-    fn cross_entropy_loss(&self, logits: &[Vec<f64>], target: &[usize]) -> f64 {
-        let mut loss = 0.0;
+    pub fn save_checkpoint(&self, path: &str) -> Result<()> {
+        utils::save_model(self, path)
+    }
 
-        for (logit, &target_id) in logits.iter().zip(target.iter()) {
-            let probs = softmax(logit);
-            loss -= probs[target_id].ln();
-        }
+    pub fn load_checkpoint(path: &str) -> Result<Self> {
+        utils::load_model(path)
+    }
 
-        loss / logits.len() as f64
+    /// Loads a checkpoint for use with `config`, which may ask for a longer context than the
+    /// checkpoint was trained with: `config.position_scaling` is applied first, then
+    /// `config.max_seq_len`. Fine-tuning the result briefly at the new length recovers most of
+    /// the quality lost to the stretched positions.
+    ///
+    /// The config is validated first, and its architecture must match the checkpoint's;
+    /// otherwise this is an `Error::Checkpoint`. Like `Model::new`, it reseeds the thread's
+    /// random stream from `config.seed`, so a fine-tune is as reproducible as a fresh run.
+    pub fn from_checkpoint(path: &str, config: &Config) -> Result<Self> {
+        config.validate()?;
+        let mut model = Self::load_checkpoint(path)?;
+        let layers = model.transformer.layers();
+        let attention = layers.first().map(|layer| layer.attention());
+        let sizes = [
+            ("vocab_size", model.linear.output_size, config.vocab_size),
+            ("embedding_dim", model.linear.input_size, config.embedding_dim),
+            ("num_layers", layers.len(), config.num_layers),
+            ("num_heads", attention.map_or(0, |a| a.num_heads()), config.num_heads),
+            ("num_kv_heads", attention.map_or(0, |a| a.num_kv_heads()), config.kv_heads()),
+            (
+                "feed_forward_dim",
+                layers.first().map_or(0, |layer| layer.feed_forward().hidden_dim()),
+                config.ffn_type.hidden_dim(config.feed_forward_dim),
+            ),
+        ];
+        let mut mismatches = sizes.map(|(field, saved, configured)| (field, saved.to_string(), configured.to_string())).to_vec();
+        mismatches.push((
+            "position_encoding",
+            format!("{:?}", model.position_encoding),
+            format!("{:?}", config.position_encoding),
+        ));
+        // Every layer has the same kind of attention and feed-forward block, except for the
+        // window, which `global_attention_every` lifts on some layers.
+        if let Some(layer) = layers.first() {
+            let (attention, feed_forward) = (layer.attention(), layer.feed_forward());
+            mismatches.push(("ffn_type", format!("{:?}", feed_forward.ffn_type()), format!("{:?}", config.ffn_type)));
+            mismatches.push((
+                "activation",
+                format!("{:?}", feed_forward.activation()),
+                format!("{:?}", config.feed_forward_activation()),
+            ));
+            mismatches.push(("causal", attention.is_causal().to_string(), config.causal.to_string()));
+            if let Some(rotary) = attention.rotary() {
+                mismatches.push(("rope_base", rotary.base().to_string(), config.rope_base.to_string()));
+            }
+        }
+        let saved_windows = layers.iter().map(|layer| layer.attention().window()).collect::<Vec<_>>();
+        let configured_windows = (0..config.num_layers).map(|i| config.layer_attention_window(i)).collect::<Vec<_>>();
+        mismatches.push(("attention windows", format!("{:?}", saved_windows), format!("{:?}", configured_windows)));
+        if let Some((field, saved, configured)) = mismatches.into_iter().find(|(_, saved, configured)| saved != configured) {
+            return Err(Error::Checkpoint {
+                path: path.to_string(),
+                message: format!("the checkpoint has {} {}, the config {}", field, saved, configured),
+            });
+        }
+        rng::manual_seed(config.seed);
+        model.set_position_scaling(config.position_scaling)?;
+        model.set_max_seq_len(config.max_seq_len);
+        Ok(model)
     }
+}
 
-    fn save_checkpoint(&self, path: &str) {
-        // Implement saving the model checkpoint to a file
-        // You can use serde and bincode to serialize the model
+// Token embeddings of `[batch][seq]` ids plus the position vectors of positions
+// `offset..offset + seq`, if any; positions past `max_seq_len` are an error either way.
+pub(crate) fn embed_tokens<T: Float>(
+    embedding: &Embedding<T>,
+    position_table: Option<&PositionTable<T>>,
+    max_seq_len: usize,
+    input: &[Vec<usize>],
+    offset: usize,
+) -> Result<Var<T>> {
+    let batch_size = input.len();
+    let seq_len = input.first().map_or(0, |s| s.len());
+    let embeddings = embedding.forward(&input.concat())?;
+    let embedding_dim = embeddings.shape()[1];
+    let embeddings = embeddings.reshape(&[batch_size, seq_len, embedding_dim]);
+    let positions = position_table.map(|table| table.forward_at(offset, seq_len)).transpose()?;
+    if offset + seq_len > max_seq_len {
+        let expected = format!("[_, <= {}]", max_seq_len);
+        return Err(Error::shape("Model input", expected, &[batch_size, offset + seq_len]));
     }
+    Ok(match positions {
+        Some(positions) => embeddings.add(&positions),
+        None => embeddings,
+    })
+}
 
-    fn load_checkpoint(path: &str) -> Self {
-        // Implement loading the model checkpoint from a file
-        // You can use serde and bincode to deserialize the model
-        unimplemented!()
+// The mean cross-entropy of `[batch, seq, vocab_size]` logits against `target`, skipping
+// `ignore_index`.
+pub(crate) fn cross_entropy_loss<T: Float>(logits: &Var<T>, target: &[Vec<usize>], ignore_index: Option<usize>) -> Result<Var<T>> {
+    let target = target.concat();
+    let vocab_size = logits.shape()[2];
+    let counted = target.iter().map(|&id| Some(id) != ignore_index).collect::<Vec<_>>();
+    if let Some((&token_id, _)) = target.iter().zip(&counted).find(|&(&id, &c)| c && id >= vocab_size) {
+        return Err(Error::VocabMismatch { vocab_size, token_id });
     }
+
+    // Ignored targets may lie outside the vocabulary, so gather a valid id and zero it out.
+    let gather_ids = target.iter().zip(&counted).map(|(&id, &c)| if c { id } else { 0 }).collect::<Vec<_>>();
+    let weights = counted.iter().map(|&c| if c { T::one() } else { T::zero() }).collect();
+    let weights = Var::constant(Tensor::new(weights, &logits.shape()[..2]));
+    let log_probs = logits.log_softmax(2).gather_last(&gather_ids).mul(&weights);
+
+    let num_tokens = counted.iter().filter(|&&c| c).count().max(1);
+    Ok(log_probs.sum().scale(-1.0 / num_tokens as f64))
 }
 
-fn softmax(x: &[f64]) -> Vec<f64> {
-    let max_x = x.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let exp_x: Vec<f64> = x.iter().map(|&xi| (xi - max_x).exp()).collect();
-    let sum_exp_x = exp_x.iter().sum::<f64>();
-    exp_x.into_iter().map(|exp_xi| exp_xi / sum_exp_x).collect()
+// Checks that `batch` is a non-empty `[batch_size][seq_len]` grid.
+pub(crate) fn check_batch<E>(layer: &str, batch: &[Vec<E>], batch_size: usize, seq_len: usize) -> Result<()> {
+    if batch_size == 0 || seq_len == 0 {
+        return Err(Error::shape(layer, "a non-empty [batch, seq] grid", &[batch_size, seq_len]));
+    }
+    if batch.len() != batch_size {
+        return Err(Error::shape(layer, format!("[{}, {}]", batch_size, seq_len), &[batch.len(), seq_len]));
+    }
+    if let Some(row) = batch.iter().find(|row| row.len() != seq_len) {
+        return Err(Error::shape(layer, format!("[{}, {}]", batch_size, seq_len), &[batch_size, row.len()]));
+    }
+    Ok(())
 }
 
-fn sample_multinomial(probs: &[f64]) -> usize {
-    let mut rng = rand::thread_rng();
-    let mut cum_probs = probs.to_vec();
-    for i in 1..cum_probs.len() {
-        cum_probs[i] += cum_probs[i - 1];
+impl<T: Float> Module<T> for Model<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("embedding", self.embedding.named_parameters()));
+        if let Some(position_table) = &self.position_table {
+            params.extend(prefixed("position_table", position_table.named_parameters()));
+        }
+        params.extend(prefixed("transformer", self.transformer.named_parameters()));
+        params.extend(prefixed("layer_norm", self.layer_norm.named_parameters()));
+        params.extend(prefixed("linear", self.linear.named_parameters()));
+        params
     }
-    let r: f64 = rng.gen();
-    cum_probs.iter().position(|&p| p > r).unwrap()
-}
\ No newline at end of file
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("embedding", self.embedding.named_parameters_mut()));
+        if let Some(position_table) = &mut self.position_table {
+            params.extend(prefixed("position_table", position_table.named_parameters_mut()));
+        }
+        params.extend(prefixed("transformer", self.transformer.named_parameters_mut()));
+        params.extend(prefixed("layer_norm", self.layer_norm.named_parameters_mut()));
+        params.extend(prefixed("linear", self.linear.named_parameters_mut()));
+        params
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.transformer.set_training(training);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::float::Float;
+use crate::parameter::Parameter;
+
+/// A layer that owns parameters, registered under hierarchical dot-separated names such as
+/// `transformer.layers.3.attention.query_matrix.weight`.
+pub trait Module<T: Float = f32> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)>;
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)>;
+
+    /// Switches between training and evaluation behaviour. Modules with children must forward
+    /// this to each of them.
+    fn set_training(&mut self, _training: bool) {}
+
+    fn train(&mut self) {
+        self.set_training(true);
+    }
+
+    fn eval(&mut self) {
+        self.set_training(false);
+    }
+
+    fn parameters(&self) -> Vec<&Parameter<T>> {
+        self.named_parameters().into_iter().map(|(_, p)| p).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Parameter<T>> {
+        self.named_parameters_mut().into_iter().map(|(_, p)| p).collect()
+    }
+
+    fn zero_grad(&self) {
+        for p in self.parameters() {
+            p.zero_grad();
+        }
+    }
+
+    fn num_parameters(&self) -> usize {
+        self.parameters().iter().map(|p| p.numel()).sum()
+    }
+}
+
+/// Prefixes the names of a child module's parameters with the child's field name.
+pub fn prefixed<P>(prefix: &str, params: Vec<(String, P)>) -> Vec<(String, P)> {
+    params
+        .into_iter()
+        .map(|(name, p)| (format!("{}.{}", prefix, name), p))
+        .collect()
+}
+
+/// The leaf entry for a parameter owned directly by a module.
+pub fn own<T: Float, P: std::ops::Deref<Target = Parameter<T>>>(param: P) -> (String, P) {
+    (param.name().to_string(), param)
+}
@@ -1,16 +1,20 @@
-use crate::model::Model;
+use crate::float::Float;
+use crate::module::Module;
+use crate::tensor::Tensor;
 
-pub struct AdamOptimizer {
+/// Adam with bias correction. The moment estimates are stored in the model's float type, while
+/// the update itself is computed in `f64`.
+pub struct AdamOptimizer<T: Float = f32> {
     learning_rate: f64,
     beta1: f64,
     beta2: f64,
     epsilon: f64,
-    m: Vec<Vec<f64>>,
-    v: Vec<Vec<f64>>,
+    m: Vec<Tensor<T>>,
+    v: Vec<Tensor<T>>,
     t: usize,
 }
 
-impl AdamOptimizer {
+impl<T: Float> AdamOptimizer<T> {
     pub fn new(learning_rate: f64) -> Self {
         Self {
             learning_rate,
@@ -23,24 +27,38 @@ impl AdamOptimizer {
         }
     }
 
-    pub fn step(&mut self, model: &mut Model) {
+    /// Updates every parameter of `model` from its accumulated gradient. Parameters that received
+    /// no gradient are left untouched.
+    pub fn step<M: Module<T>>(&mut self, model: &mut M) {
+        let params = model.parameters_mut();
         if self.m.is_empty() {
-            self.m = model.parameters().iter().map(|p| vec![0.0; p.len()]).collect();
-            self.v = model.parameters().iter().map(|p| vec![0.0; p.len()]).collect();
+            self.m = params.iter().map(|p| Tensor::zeros(&p.shape())).collect();
+            self.v = params.iter().map(|p| Tensor::zeros(&p.shape())).collect();
         }
+        assert_eq!(params.len(), self.m.len(), "model parameters changed between optimizer steps");
 
         self.t += 1;
+        let bias_correction1 = 1.0 - self.beta1.powi(self.t as i32);
+        let bias_correction2 = 1.0 - self.beta2.powi(self.t as i32);
 
-        for (p, m, v) in izip!(model.parameters_mut(), &mut self.m, &mut self.v) {
-            for (p_i, m_i, v_i) in izip!(p, m, v) {
-                *m_i = self.beta1 * *m_i + (1.0 - self.beta1) * *p_i;
-                *v_i = self.beta2 * *v_i + (1.0 - self.beta2) * p_i.powi(2);
+        for ((p, m), v) in params.into_iter().zip(&mut self.m).zip(&mut self.v) {
+            let Some(grad) = p.grad().map(|g| g.contiguous()) else {
+                continue;
+            };
+            let mut value = p.value_mut();
+            let iter = value.data_mut().iter_mut().zip(grad.data()).zip(m.data_mut()).zip(v.data_mut());
+            for (((p_i, &g_i), m_i), v_i) in iter {
+                let g_i = g_i.to_f64();
+                let m_new = self.beta1 * m_i.to_f64() + (1.0 - self.beta1) * g_i;
+                let v_new = self.beta2 * v_i.to_f64() + (1.0 - self.beta2) * g_i.powi(2);
+                *m_i = T::from_f64(m_new);
+                *v_i = T::from_f64(v_new);
 
-                let m_hat = *m_i / (1.0 - self.beta1.powi(self.t as i32));
-                let v_hat = *v_i / (1.0 - self.beta2.powi(self.t as i32));
+                let m_hat = m_new / bias_correction1;
+                let v_hat = v_new / bias_correction2;
 
-                *p_i -= self.learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+                *p_i = T::from_f64(p_i.to_f64() - self.learning_rate * m_hat / (v_hat.sqrt() + self.epsilon));
             }
         }
     }
-}
\ No newline at end of file
+}
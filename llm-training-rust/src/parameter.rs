@@ -0,0 +1,73 @@
+use std::cell::{Ref, RefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::autograd::Var;
+use crate::float::Float;
+use crate::tensor::Tensor;
+
+/// A named, trainable tensor together with its gradient buffer.
+///
+/// The gradient is filled by `Var::backward` and stays in place until `zero_grad`, so several
+/// backward passes accumulate.
+pub struct Parameter<T: Float = f32> {
+    name: String,
+    var: Var<T>,
+}
+
+impl<T: Float> Parameter<T> {
+    pub fn new(name: &str, value: Tensor<T>) -> Self {
+        Self {
+            name: name.to_string(),
+            var: Var::leaf(value),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The parameter as a graph leaf, for use in a forward pass.
+    pub fn var(&self) -> &Var<T> {
+        &self.var
+    }
+
+    pub fn value(&self) -> Ref<'_, Tensor<T>> {
+        self.var.value()
+    }
+
+    pub fn value_mut(&mut self) -> RefMut<'_, Tensor<T>> {
+        self.var.value_mut()
+    }
+
+    pub fn shape(&self) -> Vec<usize> {
+        self.var.shape()
+    }
+
+    pub fn numel(&self) -> usize {
+        self.value().numel()
+    }
+
+    /// The accumulated gradient, or `None` if nothing has flowed into this parameter yet.
+    pub fn grad(&self) -> Option<Tensor<T>> {
+        self.var.grad()
+    }
+
+    pub fn zero_grad(&self) {
+        self.var.zero_grad();
+    }
+}
+
+// Only the name and value are persisted; gradients start out empty after loading.
+impl<T: Float> Serialize for Parameter<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.name, &*self.value()).serialize(serializer)
+    }
+}
+
+impl<'de, T: Float> Deserialize<'de> for Parameter<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, value) = <(String, Tensor<T>)>::deserialize(deserializer)?;
+        Ok(Self::new(&name, value))
+    }
+}
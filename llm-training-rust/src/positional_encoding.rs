@@ -1,25 +1,264 @@
-use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
 
-pub struct PositionalEncoding {
-    pub encodings: Vec<Vec<f64>>,
+use crate::autograd::Var;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::init::Init;
+use crate::module::{own, Module};
+use crate::parameter::Parameter;
+use crate::tensor::Tensor;
+
+/// How a model encodes token positions, chosen by `Config::position_encoding`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionEncoding {
+    /// Fixed sinusoidal vectors added to the token embeddings (`PositionalEncoding`).
+    #[default]
+    Sinusoidal,
+    /// Rotations of the queries and keys inside attention (`RotaryEmbedding`).
+    Rope,
+    /// Per-head linear distance penalties on the attention scores (`alibi_bias`), which carry
+    /// over to sequences longer than those seen in training.
+    Alibi,
+    /// A trained vector per position added to the token embeddings (`LearnedPositionalEmbedding`).
+    Learned,
+}
+
+/// Stretches a position encoding over a longer context than it was trained on, so a model
+/// trained at one `max_seq_len` can be fine-tuned or run at several times that length. Applies
+/// to the sinusoidal table and to rotary embeddings, whose frequencies it rescales; see
+/// `frequency`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum PositionScaling {
+    /// Position interpolation: every frequency is divided by `factor`, so `factor` times as many
+    /// positions fit in the angles seen in training, at the cost of finer position resolution.
+    Linear { factor: f64 },
+    /// NTK-aware scaling: the base grows to `base * factor^(dim / (dim - 2))`, which interpolates
+    /// the slowest frequency by `factor` while leaving the fastest almost untouched.
+    Ntk { factor: f64 },
+    /// YaRN: frequencies that complete many turns within `original_max_seq_len` are kept,
+    /// those that complete less than one are interpolated by `factor`, and the ones between are
+    /// blended. Rotary attention also sharpens its scores by `attention_factor`.
+    Yarn { factor: f64, original_max_seq_len: usize },
+}
+
+// YaRN's ramp between dimension pairs completing `YARN_BETA_SLOW` and `YARN_BETA_FAST` turns
+// over the original context.
+const YARN_BETA_FAST: f64 = 32.0;
+const YARN_BETA_SLOW: f64 = 1.0;
+
+impl PositionScaling {
+    pub fn factor(&self) -> f64 {
+        match *self {
+            Self::Linear { factor } | Self::Ntk { factor } | Self::Yarn { factor, .. } => factor,
+        }
+    }
+
+    /// The multiplier YaRN applies to rotated queries and keys, `0.1 * ln(factor) + 1`, which
+    /// offsets the flatter attention distributions of longer contexts. `1` for other methods.
+    pub fn attention_factor(&self) -> f64 {
+        match *self {
+            Self::Yarn { factor, .. } => 0.1 * factor.ln() + 1.0,
+            Self::Linear { .. } | Self::Ntk { .. } => 1.0,
+        }
+    }
+
+    /// Rejects a factor below 1 and a YaRN `original_max_seq_len` of 0 with
+    /// `Error::InvalidConfig`.
+    pub fn check(&self) -> Result<()> {
+        if !(self.factor() >= 1.0 && self.factor().is_finite()) {
+            return Err(Error::InvalidConfig(format!(
+                "position_scaling factor must be at least 1, got {}",
+                self.factor()
+            )));
+        }
+        if let Self::Yarn { original_max_seq_len: 0, .. } = self {
+            return Err(Error::InvalidConfig("yarn original_max_seq_len must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The angle per position of dimension pair `pair` in a `dim`-dimensional sinusoidal or rotary
+/// encoding, `base^(-2 * pair / dim)`, adjusted by `scaling`.
+pub fn frequency(base: f64, dim: usize, pair: usize, scaling: Option<PositionScaling>) -> f64 {
+    let exponent = -2.0 * pair as f64 / dim as f64;
+    let frequency = base.powf(exponent);
+    match scaling {
+        None => frequency,
+        Some(PositionScaling::Linear { factor }) => frequency / factor,
+        Some(PositionScaling::Ntk { factor }) if dim > 2 => (base * factor.powf(dim as f64 / (dim - 2) as f64)).powf(exponent),
+        Some(PositionScaling::Ntk { .. }) => frequency,
+        Some(PositionScaling::Yarn { factor, original_max_seq_len }) => {
+            let turns = original_max_seq_len as f64 * frequency / std::f64::consts::TAU;
+            let keep = ((turns - YARN_BETA_SLOW) / (YARN_BETA_FAST - YARN_BETA_SLOW)).clamp(0.0, 1.0);
+            frequency * (keep + (1.0 - keep) / factor)
+        }
+    }
+}
+
+/// The table of position vectors a model adds to its token embeddings, for the encodings that
+/// have one.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum PositionTable<T: Float = f32> {
+    Sinusoidal(PositionalEncoding<T>),
+    Learned(LearnedPositionalEmbedding<T>),
+}
+
+impl<T: Float> PositionTable<T> {
+    /// The table for `config.position_encoding`, or `None` for RoPE and ALiBi, which encode
+    /// positions inside attention instead.
+    pub fn for_config(config: &Config) -> Option<Self> {
+        match config.position_encoding {
+            PositionEncoding::Sinusoidal => Some(Self::Sinusoidal(PositionalEncoding::with_scaling(
+                config.max_seq_len,
+                config.embedding_dim,
+                config.position_scaling,
+            ))),
+            PositionEncoding::Learned => Some(Self::Learned(LearnedPositionalEmbedding::new(config.max_seq_len, config.embedding_dim))),
+            PositionEncoding::Rope | PositionEncoding::Alibi => None,
+        }
+    }
+
+    /// The `[seq_len, embedding_dim]` vectors of positions `offset..offset + seq_len`.
+    pub fn forward_at(&self, offset: usize, seq_len: usize) -> Result<Var<T>> {
+        match self {
+            Self::Sinusoidal(table) => Ok(Var::constant(table.forward_at(offset, seq_len)?)),
+            Self::Learned(table) => table.forward_at(offset, seq_len),
+        }
+    }
+
+    pub fn max_seq_len(&self) -> usize {
+        match self {
+            Self::Sinusoidal(table) => table.max_seq_len(),
+            Self::Learned(table) => table.max_seq_len(),
+        }
+    }
+}
+
+impl<T: Float> Module<T> for PositionTable<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        match self {
+            Self::Sinusoidal(_) => Vec::new(),
+            Self::Learned(table) => table.named_parameters(),
+        }
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        match self {
+            Self::Sinusoidal(_) => Vec::new(),
+            Self::Learned(table) => table.named_parameters_mut(),
+        }
+    }
 }
 
-impl PositionalEncoding {
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PositionalEncoding<T: Float = f32> {
+    pub encodings: Tensor<T>,
+}
+
+impl<T: Float> PositionalEncoding<T> {
     pub fn new(max_seq_len: usize, embedding_dim: usize) -> Self {
-        let mut encodings = Vec::with_capacity(max_seq_len);
+        Self::with_scaling(max_seq_len, embedding_dim, None)
+    }
+
+    /// The table with its frequencies adjusted by `scaling`, e.g. to cover more positions than
+    /// the model was trained on.
+    pub fn with_scaling(max_seq_len: usize, embedding_dim: usize, scaling: Option<PositionScaling>) -> Self {
+        let mut data = Vec::with_capacity(max_seq_len * embedding_dim);
         for pos in 0..max_seq_len {
-            let mut encoding = Vec::with_capacity(embedding_dim);
             for i in 0..embedding_dim {
-                let angle = pos as f64 / (10000.0_f64).powf((2 * i) as f64 / embedding_dim as f64);
-                encoding.push(angle.sin());
-                encoding.push(angle.cos());
+                let angle = pos as f64 * frequency(10000.0, embedding_dim, i / 2, scaling);
+                data.push(T::from_f64(if i % 2 == 0 { angle.sin() } else { angle.cos() }));
             }
-            encodings.push(encoding);
         }
+        let encodings = Tensor::new(data, &[max_seq_len, embedding_dim]);
         Self { encodings }
     }
 
-    pub fn forward(&self, seq_len: usize) -> Vec<Vec<f64>> {
-        self.encodings[..seq_len].to_vec()
+    /// The first `seq_len` rows, `[seq_len, embedding_dim]`.
+    pub fn forward(&self, seq_len: usize) -> Result<Tensor<T>> {
+        self.forward_at(0, seq_len)
+    }
+
+    /// The rows for positions `offset..offset + seq_len`, for decoding that continues a sequence.
+    pub fn forward_at(&self, offset: usize, seq_len: usize) -> Result<Tensor<T>> {
+        let max_seq_len = self.encodings.dim(0);
+        if offset + seq_len > max_seq_len {
+            return Err(Error::shape("PositionalEncoding", format!("[<= {}]", max_seq_len), &[offset + seq_len]));
+        }
+        Ok(self.encodings.narrow(0, offset, seq_len))
+    }
+
+    pub fn max_seq_len(&self) -> usize {
+        self.encodings.dim(0)
+    }
+}
+
+/// A trainable `[max_seq_len, embedding_dim]` table of position vectors, as in GPT-2. Unlike the
+/// sinusoidal table it has nothing to offer for positions it was not trained on.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LearnedPositionalEmbedding<T: Float = f32> {
+    embedding_matrix: Parameter<T>,
+}
+
+impl<T: Float> LearnedPositionalEmbedding<T> {
+    pub fn new(max_seq_len: usize, embedding_dim: usize) -> Self {
+        let init = Init::Normal(0.02).tensor(&[max_seq_len, embedding_dim], max_seq_len, embedding_dim);
+        let embedding_matrix = Parameter::new("embedding_matrix", init);
+
+        Self { embedding_matrix }
+    }
+
+    /// The rows for positions `offset..offset + seq_len`, `[seq_len, embedding_dim]`. Their
+    /// gradient flows back into the table.
+    pub fn forward_at(&self, offset: usize, seq_len: usize) -> Result<Var<T>> {
+        let max_seq_len = self.max_seq_len();
+        if offset + seq_len > max_seq_len {
+            let expected = format!("[<= {}]", max_seq_len);
+            return Err(Error::shape("LearnedPositionalEmbedding", expected, &[offset + seq_len]));
+        }
+        Ok(self.embedding_matrix.var().narrow(0, offset, seq_len))
+    }
+
+    pub fn max_seq_len(&self) -> usize {
+        self.embedding_matrix.shape()[0]
     }
-}
\ No newline at end of file
+
+    /// Grows the table to `max_seq_len` rows by position interpolation: row `p` becomes the
+    /// trained table read at position `p * old_len / max_seq_len`, linearly interpolated between
+    /// its neighbouring rows. A shorter `max_seq_len` leaves the table as it is.
+    pub fn interpolate(&mut self, max_seq_len: usize) {
+        let old_len = self.max_seq_len();
+        if max_seq_len <= old_len {
+            return;
+        }
+        let table = self.embedding_matrix.value().clone();
+        let rows = (0..max_seq_len)
+            .map(|p| {
+                let position = p as f64 * old_len as f64 / max_seq_len as f64;
+                let (low, frac) = (position.floor() as usize, position.fract());
+                let high = (low + 1).min(old_len - 1);
+                let low_row = table.narrow(0, low, 1).scale(1.0 - frac);
+                low_row.add(&table.narrow(0, high, 1).scale(frac))
+            })
+            .collect::<Vec<_>>();
+        let table = Tensor::cat(&rows.iter().collect::<Vec<_>>(), 0);
+        self.embedding_matrix = Parameter::new("embedding_matrix", table);
+    }
+}
+
+impl<T: Float> Module<T> for LearnedPositionalEmbedding<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        vec![own(&self.embedding_matrix)]
+    }
+
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        vec![own(&mut self.embedding_matrix)]
+    }
+}
@@ -0,0 +1,25 @@
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseeds the random stream used for weight initialization, dropout masks, data shuffling and
+/// sampling on the current thread. Every draw after this point is a pure function of `seed`.
+///
+/// `Model::new` calls this with `Config::seed`, so two models built from the same config start
+/// from identical weights and follow identical training and generation trajectories.
+pub fn manual_seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Runs `f` with the current thread's random stream.
+///
+/// Borrow it once around a whole loop rather than once per draw; `f` must not call back into
+/// `with_rng`.
+pub fn with_rng<R>(f: impl FnOnce(&mut StdRng) -> R) -> R {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::autograd::Var;
+use crate::float::Float;
+use crate::positional_encoding::{frequency, PositionScaling};
+use crate::tensor::Tensor;
+
+/// Rotary position embeddings (RoPE), applied to queries and keys inside `Attention`.
+///
+/// Each head vector is split into halves `(x1, x2)`, and dimension pair `(x1[i], x2[i])` is
+/// rotated by the angle `position * base^(-2i / head_dim)`. The dot product of a rotated query
+/// and key then depends only on their distance, and no position vector is added to embeddings.
+///
+/// A `PositionScaling` adjusts the frequencies to cover a longer context; the scaling is saved
+/// with the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotaryEmbedding {
+    head_dim: usize,
+    base: f64,
+    scaling: Option<PositionScaling>,
+}
+
+impl RotaryEmbedding {
+    pub fn new(head_dim: usize, base: f64) -> Self {
+        assert!(head_dim.is_multiple_of(2), "rotary embeddings need an even head_dim, got {}", head_dim);
+        Self {
+            head_dim,
+            base,
+            scaling: None,
+        }
+    }
+
+    pub fn base(&self) -> f64 {
+        self.base
+    }
+
+    pub fn scaling(&self) -> Option<PositionScaling> {
+        self.scaling
+    }
+
+    pub fn set_scaling(&mut self, scaling: Option<PositionScaling>) {
+        self.scaling = scaling;
+    }
+
+    /// The rotation angle of dimension pair `i` per position.
+    pub fn frequency(&self, i: usize) -> f64 {
+        frequency(self.base, self.head_dim, i, self.scaling)
+    }
+
+    /// The `[seq_len, head_dim]` cosine and sine tables for positions `offset..offset + seq_len`,
+    /// with each pair's value repeated in both halves. Under YaRN scaling both are multiplied by
+    /// its attention factor.
+    pub fn tables<T: Float>(&self, offset: usize, seq_len: usize) -> (Tensor<T>, Tensor<T>) {
+        let half = self.head_dim / 2;
+        let magnitude = self.scaling.map_or(1.0, |s| s.attention_factor());
+        let mut cos = Vec::with_capacity(seq_len * self.head_dim);
+        let mut sin = Vec::with_capacity(seq_len * self.head_dim);
+        for pos in offset..offset + seq_len {
+            for i in 0..self.head_dim {
+                let angle = pos as f64 * self.frequency(i % half);
+                cos.push(T::from_f64(magnitude * angle.cos()));
+                sin.push(T::from_f64(magnitude * angle.sin()));
+            }
+        }
+        let shape = [seq_len, self.head_dim];
+        (Tensor::new(cos, &shape), Tensor::new(sin, &shape))
+    }
+
+    /// Rotates `[.., seq, head_dim]` vectors as if they sat at positions `offset..offset + seq`.
+    ///
+    /// The rotation is built from differentiable ops, so the backward pass rotates the gradient
+    /// by the opposite angle.
+    pub fn apply<T: Float>(&self, x: &Var<T>, offset: usize) -> Var<T> {
+        let shape = x.shape();
+        let axis = shape.len() - 1;
+        let (cos, sin) = self.tables(offset, shape[axis - 1]);
+        let half = self.head_dim / 2;
+
+        // rotate_half([x1, x2]) = [-x2, x1]
+        let rotated = Var::cat(&[&x.narrow(axis, half, half).scale(-1.0), &x.narrow(axis, 0, half)], axis);
+        x.mul(&Var::constant(cos)).add(&rotated.mul(&Var::constant(sin)))
+    }
+}
@@ -0,0 +1,18 @@
+use crate::float::Float;
+
+/// SiLU (also called Swish), `x * sigmoid(x)`.
+pub fn silu<T: Float>(x: T) -> T {
+    let x = x.to_f64();
+    T::from_f64(x * sigmoid(x))
+}
+
+/// The derivative of `silu` at `input`, multiplied by `grad_output`.
+pub fn silu_backward<T: Float>(grad_output: T, input: T) -> T {
+    let x = input.to_f64();
+    let s = sigmoid(x);
+    grad_output * T::from_f64(s * (1.0 + x * (1.0 - s)))
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+
+use crate::float::Float;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Tensor<T: Float = f32> {
+    data: Vec<T>,
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+}
+
+fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
+pub fn broadcast_shapes(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let ndim = a.len().max(b.len());
+    let mut shape = Vec::with_capacity(ndim);
+    for i in 0..ndim {
+        let da = if i + a.len() >= ndim { a[i + a.len() - ndim] } else { 1 };
+        let db = if i + b.len() >= ndim { b[i + b.len() - ndim] } else { 1 };
+        assert!(da == db || da == 1 || db == 1, "cannot broadcast shapes {:?} and {:?}", a, b);
+        shape.push(if da == 1 { db } else { da });
+    }
+    shape
+}
+
+// Iterates the storage offsets of a strided layout in row-major logical order.
+struct Offsets {
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    index: Vec<usize>,
+    offset: usize,
+    remaining: usize,
+}
+
+impl Offsets {
+    fn new(shape: &[usize], strides: &[usize]) -> Self {
+        Self {
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            index: vec![0; shape.len()],
+            offset: 0,
+            remaining: shape.iter().product(),
+        }
+    }
+}
+
+impl Iterator for Offsets {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let current = self.offset;
+        for d in (0..self.shape.len()).rev() {
+            self.index[d] += 1;
+            self.offset += self.strides[d];
+            if self.index[d] < self.shape[d] {
+                break;
+            }
+            self.offset -= self.strides[d] * self.shape[d];
+            self.index[d] = 0;
+        }
+        Some(current)
+    }
+}
+
+impl<T: Float> Tensor<T> {
+    pub fn new(data: Vec<T>, shape: &[usize]) -> Self {
+        assert_eq!(
+            data.len(),
+            shape.iter().product::<usize>(),
+            "data length does not match shape {:?}",
+            shape
+        );
+        Self {
+            data,
+            shape: shape.to_vec(),
+            strides: contiguous_strides(shape),
+        }
+    }
+
+    pub fn full(shape: &[usize], value: T) -> Self {
+        Self::new(vec![value; shape.iter().product()], shape)
+    }
+
+    pub fn zeros(shape: &[usize]) -> Self {
+        Self::full(shape, T::zero())
+    }
+
+    pub fn ones(shape: &[usize]) -> Self {
+        Self::full(shape, T::one())
+    }
+
+    pub fn scalar(value: T) -> Self {
+        Self::new(vec![value], &[])
+    }
+
+    pub fn from_rows(rows: &[Vec<T>]) -> Self {
+        let cols = rows.first().map_or(0, |r| r.len());
+        assert!(rows.iter().all(|r| r.len() == cols), "rows have different lengths");
+        Self::new(rows.concat(), &[rows.len(), cols])
+    }
+
+    pub fn item(&self) -> T {
+        assert_eq!(self.numel(), 1, "item() called on a tensor of shape {:?}", self.shape);
+        self.data[0]
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn dim(&self, axis: usize) -> usize {
+        self.shape[axis]
+    }
+
+    pub fn numel(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_contiguous(&self) -> bool {
+        self.strides == contiguous_strides(&self.shape)
+    }
+
+    pub fn contiguous(&self) -> Tensor<T> {
+        if self.is_contiguous() {
+            return self.clone();
+        }
+        Self::new(self.to_vec(), &self.shape)
+    }
+
+    /// The underlying storage. Only meaningful in logical order for contiguous tensors.
+    pub fn data(&self) -> &[T] {
+        assert!(self.is_contiguous(), "data() called on a non-contiguous tensor");
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [T] {
+        assert!(self.is_contiguous(), "data_mut() called on a non-contiguous tensor");
+        &mut self.data
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        if self.is_contiguous() {
+            return self.data.clone();
+        }
+        Offsets::new(&self.shape, &self.strides).map(|o| self.data[o]).collect()
+    }
+
+    fn offset(&self, index: &[usize]) -> usize {
+        assert_eq!(index.len(), self.ndim(), "index {:?} does not match shape {:?}", index, self.shape);
+        index
+            .iter()
+            .zip(&self.shape)
+            .zip(&self.strides)
+            .map(|((&i, &d), &s)| {
+                assert!(i < d, "index {:?} out of bounds for shape {:?}", index, self.shape);
+                i * s
+            })
+            .sum()
+    }
+
+    pub fn get(&self, index: &[usize]) -> T {
+        self.data[self.offset(index)]
+    }
+
+    pub fn set(&mut self, index: &[usize], value: T) {
+        let offset = self.offset(index);
+        self.data[offset] = value;
+    }
+
+    pub fn reshape(&self, shape: &[usize]) -> Tensor<T> {
+        assert_eq!(
+            self.numel(),
+            shape.iter().product::<usize>(),
+            "cannot reshape {:?} into {:?}",
+            self.shape,
+            shape
+        );
+        Self::new(self.to_vec(), shape)
+    }
+
+    pub fn permute(&self, dims: &[usize]) -> Tensor<T> {
+        assert_eq!(dims.len(), self.ndim(), "permutation {:?} does not match shape {:?}", dims, self.shape);
+        let mut seen = vec![false; dims.len()];
+        for &d in dims {
+            assert!(d < dims.len() && !seen[d], "invalid permutation {:?}", dims);
+            seen[d] = true;
+        }
+        Self {
+            data: self.data.clone(),
+            shape: dims.iter().map(|&d| self.shape[d]).collect(),
+            strides: dims.iter().map(|&d| self.strides[d]).collect(),
+        }
+    }
+
+    pub fn transpose(&self, a: usize, b: usize) -> Tensor<T> {
+        let mut dims = (0..self.ndim()).collect::<Vec<_>>();
+        dims.swap(a, b);
+        self.permute(&dims)
+    }
+
+    pub fn broadcast_to(&self, shape: &[usize]) -> Tensor<T> {
+        assert!(shape.len() >= self.ndim(), "cannot broadcast {:?} to {:?}", self.shape, shape);
+        let lead = shape.len() - self.ndim();
+        let mut strides = vec![0; shape.len()];
+        for (i, (&d, &s)) in self.shape.iter().zip(&self.strides).enumerate() {
+            assert!(d == shape[lead + i] || d == 1, "cannot broadcast {:?} to {:?}", self.shape, shape);
+            strides[lead + i] = if d == 1 { 0 } else { s };
+        }
+        let data = Offsets::new(shape, &strides).map(|o| self.data[o]).collect();
+        Self::new(data, shape)
+    }
+
+    pub fn map(&self, f: impl FnMut(T) -> T) -> Tensor<T> {
+        Self::new(self.to_vec().into_iter().map(f).collect(), &self.shape)
+    }
+
+    pub fn zip_map(&self, other: &Tensor<T>, mut f: impl FnMut(T, T) -> T) -> Tensor<T> {
+        if self.shape == other.shape {
+            let data = self.to_vec().into_iter().zip(other.to_vec()).map(|(a, b)| f(a, b)).collect();
+            return Self::new(data, &self.shape);
+        }
+        let shape = broadcast_shapes(&self.shape, &other.shape);
+        let a = self.broadcast_to(&shape);
+        let b = other.broadcast_to(&shape);
+        let data = a.data.iter().zip(&b.data).map(|(&a, &b)| f(a, b)).collect();
+        Self::new(data, &shape)
+    }
+
+    pub fn add(&self, other: &Tensor<T>) -> Tensor<T> {
+        self.zip_map(other, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Tensor<T>) -> Tensor<T> {
+        self.zip_map(other, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Tensor<T>) -> Tensor<T> {
+        self.zip_map(other, |a, b| a * b)
+    }
+
+    pub fn div(&self, other: &Tensor<T>) -> Tensor<T> {
+        self.zip_map(other, |a, b| a / b)
+    }
+
+    pub fn scale(&self, factor: f64) -> Tensor<T> {
+        let factor = T::from_f64(factor);
+        self.map(|x| x * factor)
+    }
+
+    pub fn sum(&self) -> T {
+        T::from_accum(self.to_vec().into_iter().map(T::to_accum).sum())
+    }
+
+    /// Converts every element to another float type.
+    pub fn cast<U: Float>(&self) -> Tensor<U> {
+        Tensor::new(self.to_vec().into_iter().map(|x| U::from_f64(x.to_f64())).collect(), &self.shape)
+    }
+
+    // Splits a contiguous layout around `axis` into (outer, axis length, inner).
+    fn split_at_axis(&self, axis: usize) -> (usize, usize, usize) {
+        assert!(axis < self.ndim(), "axis {} out of range for shape {:?}", axis, self.shape);
+        let outer = self.shape[..axis].iter().product();
+        let inner = self.shape[axis + 1..].iter().product();
+        (outer, self.shape[axis], inner)
+    }
+
+    fn reduce_axis(&self, axis: usize, keepdim: bool, init: T, f: impl Fn(T, T) -> T) -> Tensor<T> {
+        let (outer, n, inner) = self.split_at_axis(axis);
+        let src = self.contiguous();
+        let mut out = vec![init; outer * inner];
+        for o in 0..outer {
+            for j in 0..n {
+                for i in 0..inner {
+                    let acc = &mut out[o * inner + i];
+                    *acc = f(*acc, src.data[(o * n + j) * inner + i]);
+                }
+            }
+        }
+        let mut shape = self.shape.clone();
+        if keepdim {
+            shape[axis] = 1;
+        } else {
+            shape.remove(axis);
+        }
+        Self::new(out, &shape)
+    }
+
+    pub fn sum_axis(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        self.reduce_axis(axis, keepdim, T::zero(), |a, b| a + b)
+    }
+
+    pub fn max_axis(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        self.reduce_axis(axis, keepdim, T::neg_infinity(), T::max)
+    }
+
+    pub fn mean_axis(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        let n = self.shape[axis] as f64;
+        self.sum_axis(axis, keepdim).scale(1.0 / n)
+    }
+
+    /// Sums over broadcast dimensions so the result has `shape`; the inverse of `broadcast_to`.
+    pub fn sum_to(&self, shape: &[usize]) -> Tensor<T> {
+        assert!(self.ndim() >= shape.len(), "cannot reduce {:?} to {:?}", self.shape, shape);
+        let mut out = self.clone();
+        while out.ndim() > shape.len() {
+            out = out.sum_axis(0, false);
+        }
+        for (axis, &d) in shape.iter().enumerate() {
+            if d == 1 && out.shape[axis] != 1 {
+                out = out.sum_axis(axis, true);
+            }
+        }
+        assert_eq!(out.shape, shape, "cannot reduce {:?} to {:?}", self.shape, shape);
+        out
+    }
+
+    pub fn matmul(&self, other: &Tensor<T>) -> Tensor<T> {
+        assert!(
+            self.ndim() >= 2 && other.ndim() >= 2,
+            "matmul needs at least 2-d operands, got {:?} and {:?}",
+            self.shape,
+            other.shape
+        );
+        let (m, k) = (self.shape[self.ndim() - 2], self.shape[self.ndim() - 1]);
+        let (k2, n) = (other.shape[other.ndim() - 2], other.shape[other.ndim() - 1]);
+        assert_eq!(k, k2, "matmul inner dimensions differ: {:?} x {:?}", self.shape, other.shape);
+
+        // A shared 2-d right-hand side (e.g. a weight matrix) is folded into one large product.
+        if other.ndim() == 2 {
+            let rows = self.numel() / k;
+            let data = matmul_2d(&self.contiguous().data, &other.contiguous().data, rows, k, n);
+            let mut shape = self.shape.clone();
+            *shape.last_mut().unwrap() = n;
+            return Self::new(data, &shape);
+        }
+
+        let batch = broadcast_shapes(&self.shape[..self.ndim() - 2], &other.shape[..other.ndim() - 2]);
+        let a = self.broadcast_to(&[batch.as_slice(), &[m, k]].concat());
+        let b = other.broadcast_to(&[batch.as_slice(), &[k, n]].concat());
+        let batches = batch.iter().product::<usize>();
+        let mut data = Vec::with_capacity(batches * m * n);
+        for i in 0..batches {
+            let a_i = &a.data[i * m * k..(i + 1) * m * k];
+            let b_i = &b.data[i * k * n..(i + 1) * k * n];
+            data.extend(matmul_2d(a_i, b_i, m, k, n));
+        }
+        Self::new(data, &[batch.as_slice(), &[m, n]].concat())
+    }
+
+    pub fn softmax(&self, axis: usize) -> Tensor<T> {
+        let max = self.max_axis(axis, true);
+        let exp = self.zip_map(&max, |x, m| if m == T::neg_infinity() { T::zero() } else { (x - m).exp() });
+        let sum = exp.sum_axis(axis, true);
+        exp.zip_map(&sum, |e, s| if s == T::zero() { T::zero() } else { e / s })
+    }
+
+    pub fn narrow(&self, axis: usize, start: usize, len: usize) -> Tensor<T> {
+        let (outer, n, inner) = self.split_at_axis(axis);
+        assert!(start + len <= n, "narrow {}..{} out of range for axis {} of {:?}", start, start + len, axis, self.shape);
+        let src = self.contiguous();
+        let mut data = Vec::with_capacity(outer * len * inner);
+        for o in 0..outer {
+            let begin = (o * n + start) * inner;
+            data.extend_from_slice(&src.data[begin..begin + len * inner]);
+        }
+        let mut shape = self.shape.clone();
+        shape[axis] = len;
+        Self::new(data, &shape)
+    }
+
+    pub fn cat(tensors: &[&Tensor<T>], axis: usize) -> Tensor<T> {
+        assert!(!tensors.is_empty(), "cat needs at least one tensor");
+        let mut shape = tensors[0].shape.clone();
+        for t in &tensors[1..] {
+            assert!(
+                t.ndim() == shape.len() && (0..shape.len()).all(|d| d == axis || t.shape[d] == shape[d]),
+                "cannot concatenate {:?} and {:?} along axis {}",
+                tensors[0].shape,
+                t.shape,
+                axis
+            );
+        }
+        shape[axis] = tensors.iter().map(|t| t.shape[axis]).sum();
+        let parts = tensors.iter().map(|t| t.contiguous()).collect::<Vec<_>>();
+        let (outer, _, inner) = parts[0].split_at_axis(axis);
+        let mut data = Vec::with_capacity(shape.iter().product());
+        for o in 0..outer {
+            for part in &parts {
+                let chunk = part.shape[axis] * inner;
+                data.extend_from_slice(&part.data[o * chunk..(o + 1) * chunk]);
+            }
+        }
+        Self::new(data, &shape)
+    }
+}
+
+// Accumulates in `T::Accum`, so low-precision types only round once per output element.
+fn matmul_2d<T: Float>(a: &[T], b: &[T], m: usize, k: usize, n: usize) -> Vec<T> {
+    let b = b.iter().map(|&x| x.to_accum()).collect::<Vec<_>>();
+    let mut out = vec![T::Accum::zero(); m * n];
+    for i in 0..m {
+        let row = &mut out[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a[i * k + p].to_accum();
+            for (o, &b_pj) in row.iter_mut().zip(&b[p * n..(p + 1) * n]) {
+                *o += a_ip * b_pj;
+            }
+        }
+    }
+    out.into_iter().map(T::from_accum).collect()
+}
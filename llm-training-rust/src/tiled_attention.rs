@@ -0,0 +1,293 @@
+use std::ops::Range;
+
+use crate::autograd::Var;
+use crate::float::Float;
+use crate::tensor::Tensor;
+
+/// Everything added to the raw scores of `tiled_attention`, evaluated element by element instead
+/// of being materialized as a `[batch, num_heads, seq, key_len]` tensor.
+#[derive(Debug, Clone)]
+pub struct ScoreBias<T: Float = f32> {
+    /// Hide keys after each query; queries sit at positions `past_len..past_len + seq`.
+    pub causal: bool,
+    pub past_len: usize,
+    /// Hide keys `window` or more positions away from each query.
+    pub window: Option<usize>,
+    /// Per-head ALiBi slopes; see `alibi_bias`.
+    pub alibi_slopes: Option<Vec<f64>>,
+    /// An additive mask broadcastable to `[batch, num_heads, seq, key_len]`, e.g. `padding_mask`.
+    pub mask: Option<Tensor<T>>,
+}
+
+impl<T: Float> Default for ScoreBias<T> {
+    fn default() -> Self {
+        Self {
+            causal: false,
+            past_len: 0,
+            window: None,
+            alibi_slopes: None,
+            mask: None,
+        }
+    }
+}
+
+/// Scaled dot-product attention that never holds more than one block of scores at a time.
+///
+/// `queries` is `[batch, num_heads, seq, head_dim]` and `keys`/`values` are
+/// `[batch, num_kv_heads, key_len, head_dim]`. For each query the keys are visited in blocks of
+/// `block_size`, keeping a running maximum and sum of the exponentiated scores (the online
+/// softmax), so memory grows with `seq` rather than `seq * key_len`. Only the output and the
+/// per-query log-sum-exp are saved; backward recomputes the scores block by block.
+///
+/// `dropout` is `(rate, seed)`: the keep-mask on the attention probabilities is a hash of `seed`
+/// and the element index, so backward regenerates it instead of storing it.
+pub fn tiled_attention<T: Float>(
+    queries: &Var<T>,
+    keys: &Var<T>,
+    values: &Var<T>,
+    bias: ScoreBias<T>,
+    block_size: usize,
+    dropout: Option<(f64, u64)>,
+) -> Var<T> {
+    assert!(block_size > 0, "block_size must be positive");
+    let kernel = Kernel::new(&queries.value(), &keys.value(), &values.value(), bias, block_size, dropout);
+    let (output, logsumexp) = kernel.forward();
+    let output_shape = [kernel.batch, kernel.heads, kernel.seq_len, kernel.head_dim];
+    let value = Tensor::new(output.iter().map(|&x| T::from_f64(x)).collect(), &output_shape);
+
+    let parents = vec![queries.clone(), keys.clone(), values.clone()];
+    Var::from_op(value, parents, move |grad| {
+        let grad = grad.contiguous().data().iter().map(|x| x.to_f64()).collect::<Vec<_>>();
+        let (d_queries, d_keys, d_values) = kernel.backward(&grad, &output, &logsumexp);
+        let kv_shape = [kernel.batch, kernel.kv_heads, kernel.key_len, kernel.head_dim];
+        let to_tensor = |data: Vec<f64>, shape: &[usize]| Tensor::new(data.into_iter().map(T::from_f64).collect(), shape);
+        vec![
+            to_tensor(d_queries, &output_shape),
+            to_tensor(d_keys, &kv_shape),
+            to_tensor(d_values, &kv_shape),
+        ]
+    })
+}
+
+// The saved inputs of one `tiled_attention` call, in `f64`, and the loops over them.
+struct Kernel<T: Float> {
+    queries: Vec<f64>,
+    keys: Vec<f64>,
+    values: Vec<f64>,
+    batch: usize,
+    heads: usize,
+    kv_heads: usize,
+    seq_len: usize,
+    key_len: usize,
+    head_dim: usize,
+    scale: f64,
+    block_size: usize,
+    bias: ScoreBias<T>,
+    // `bias.mask` strides over `[batch, heads, seq, key_len]`, zero along broadcast axes.
+    mask_strides: [usize; 4],
+    dropout: Option<(f64, u64)>,
+}
+
+impl<T: Float> Kernel<T> {
+    fn new(
+        queries: &Tensor<T>,
+        keys: &Tensor<T>,
+        values: &Tensor<T>,
+        bias: ScoreBias<T>,
+        block_size: usize,
+        dropout: Option<(f64, u64)>,
+    ) -> Self {
+        let (q_shape, k_shape) = (queries.shape(), keys.shape());
+        assert!(
+            q_shape.len() == 4 && k_shape.len() == 4 && values.shape() == k_shape,
+            "tiled_attention needs [batch, heads, seq, head_dim] operands, got {:?}, {:?} and {:?}",
+            q_shape,
+            k_shape,
+            values.shape()
+        );
+        assert!(
+            q_shape[0] == k_shape[0] && q_shape[3] == k_shape[3] && q_shape[1].is_multiple_of(k_shape[1]),
+            "incompatible query shape {:?} and key shape {:?}",
+            q_shape,
+            k_shape
+        );
+        let to_f64 = |t: &Tensor<T>| t.contiguous().data().iter().map(|x| x.to_f64()).collect::<Vec<_>>();
+
+        let mut mask_strides = [0; 4];
+        if let Some(mask) = &bias.mask {
+            let padded = [vec![1; 4 - mask.ndim()], mask.shape().to_vec()].concat();
+            let mut stride = 1;
+            for axis in (0..4).rev() {
+                mask_strides[axis] = if padded[axis] == 1 { 0 } else { stride };
+                stride *= padded[axis];
+            }
+        }
+        let mask = bias.mask.as_ref().map(|m| m.contiguous());
+
+        Self {
+            queries: to_f64(queries),
+            keys: to_f64(keys),
+            values: to_f64(values),
+            batch: q_shape[0],
+            heads: q_shape[1],
+            kv_heads: k_shape[1],
+            seq_len: q_shape[2],
+            key_len: k_shape[2],
+            head_dim: q_shape[3],
+            scale: 1.0 / (q_shape[3] as f64).sqrt(),
+            block_size,
+            bias: ScoreBias { mask, ..bias },
+            mask_strides,
+            dropout,
+        }
+    }
+
+    fn query_row(&self, b: usize, h: usize, i: usize) -> usize {
+        ((b * self.heads + h) * self.seq_len + i) * self.head_dim
+    }
+
+    fn key_row(&self, b: usize, h: usize, j: usize) -> usize {
+        let kv_head = h / (self.heads / self.kv_heads);
+        ((b * self.kv_heads + kv_head) * self.key_len + j) * self.head_dim
+    }
+
+    // The keys query `i` may see under the causal mask and the window.
+    fn key_range(&self, i: usize) -> Range<usize> {
+        let query = self.bias.past_len + i;
+        let mut end = if self.bias.causal { query + 1 } else { self.key_len };
+        let mut start = 0;
+        if let Some(window) = self.bias.window {
+            start = (query + 1).saturating_sub(window);
+            end = end.min(query + window);
+        }
+        start..end.min(self.key_len)
+    }
+
+    fn score(&self, b: usize, h: usize, i: usize, j: usize) -> f64 {
+        let (q, k) = (self.query_row(b, h, i), self.key_row(b, h, j));
+        let dot = (0..self.head_dim).map(|d| self.queries[q + d] * self.keys[k + d]).sum::<f64>();
+        let mut score = dot * self.scale;
+        if let Some(slopes) = &self.bias.alibi_slopes {
+            score -= slopes[h] * (self.bias.past_len + i).abs_diff(j) as f64;
+        }
+        if let Some(mask) = &self.bias.mask {
+            let [sb, sh, si, sj] = self.mask_strides;
+            score += mask.data()[b * sb + h * sh + i * si + j * sj].to_f64();
+        }
+        score
+    }
+
+    // The dropout multiplier of probability `(b, h, i, j)`: `0` or `1 / (1 - rate)`.
+    fn keep(&self, b: usize, h: usize, i: usize, j: usize) -> f64 {
+        let Some((rate, seed)) = self.dropout else {
+            return 1.0;
+        };
+        let index = (((b * self.heads + h) * self.seq_len + i) * self.key_len + j) as u64;
+        let uniform = (splitmix64(seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15)) >> 11) as f64 / (1u64 << 53) as f64;
+        if uniform < 1.0 - rate { 1.0 / (1.0 - rate) } else { 0.0 }
+    }
+
+    // Returns the `[batch, heads, seq, head_dim]` output and the per-query log-sum-exp.
+    fn forward(&self) -> (Vec<f64>, Vec<f64>) {
+        let d = self.head_dim;
+        let mut output = vec![0.0; self.batch * self.heads * self.seq_len * d];
+        let mut logsumexp = vec![f64::NEG_INFINITY; self.batch * self.heads * self.seq_len];
+        let mut scores = Vec::with_capacity(self.block_size);
+
+        for b in 0..self.batch {
+            for h in 0..self.heads {
+                for i in 0..self.seq_len {
+                    let (mut max, mut sum) = (f64::NEG_INFINITY, 0.0);
+                    let acc_start = self.query_row(b, h, i);
+                    let keys = self.key_range(i);
+
+                    for start in keys.clone().step_by(self.block_size) {
+                        let end = (start + self.block_size).min(keys.end);
+                        scores.clear();
+                        scores.extend((start..end).map(|j| self.score(b, h, i, j)));
+                        let block_max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                        if block_max == f64::NEG_INFINITY {
+                            continue;
+                        }
+
+                        // Rescale what has been accumulated so far to the new running maximum.
+                        let new_max = max.max(block_max);
+                        let correction = (max - new_max).exp();
+                        sum *= correction;
+                        output[acc_start..acc_start + d].iter_mut().for_each(|x| *x *= correction);
+
+                        for (j, &score) in (start..end).zip(&scores) {
+                            let p = (score - new_max).exp();
+                            sum += p;
+                            let weight = p * self.keep(b, h, i, j);
+                            if weight != 0.0 {
+                                let v = self.key_row(b, h, j);
+                                for x in 0..d {
+                                    output[acc_start + x] += weight * self.values[v + x];
+                                }
+                            }
+                        }
+                        max = new_max;
+                    }
+
+                    // A query that sees no key gets a zero output, like `Tensor::softmax`.
+                    if sum > 0.0 {
+                        output[acc_start..acc_start + d].iter_mut().for_each(|x| *x /= sum);
+                        logsumexp[acc_start / d] = max + sum.ln();
+                    }
+                }
+            }
+        }
+        (output, logsumexp)
+    }
+
+    // Gradients with respect to queries, keys and values, recomputing each probability as
+    // `exp(score - logsumexp)`.
+    fn backward(&self, grad: &[f64], output: &[f64], logsumexp: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let d = self.head_dim;
+        let mut d_queries = vec![0.0; self.queries.len()];
+        let mut d_keys = vec![0.0; self.keys.len()];
+        let mut d_values = vec![0.0; self.values.len()];
+
+        for b in 0..self.batch {
+            for h in 0..self.heads {
+                for i in 0..self.seq_len {
+                    let q = self.query_row(b, h, i);
+                    let lse = logsumexp[q / d];
+                    if lse == f64::NEG_INFINITY {
+                        continue;
+                    }
+                    let grad_row = &grad[q..q + d];
+                    // sum_j p_ij * dp_ij, which equals grad_i . output_i
+                    let delta = (0..d).map(|x| grad_row[x] * output[q + x]).sum::<f64>();
+
+                    for j in self.key_range(i) {
+                        let score = self.score(b, h, i, j);
+                        if score == f64::NEG_INFINITY {
+                            continue;
+                        }
+                        let p = (score - lse).exp();
+                        let keep = self.keep(b, h, i, j);
+                        let k = self.key_row(b, h, j);
+
+                        let grad_dot_value = (0..d).map(|x| grad_row[x] * self.values[k + x]).sum::<f64>();
+                        let d_score = p * (keep * grad_dot_value - delta) * self.scale;
+                        for x in 0..d {
+                            d_values[k + x] += p * keep * grad_row[x];
+                            d_queries[q + x] += d_score * self.keys[k + x];
+                            d_keys[k + x] += d_score * self.queries[q + x];
+                        }
+                    }
+                }
+            }
+        }
+        (d_queries, d_keys, d_values)
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::error::{Error, Result};
+
 pub struct Tokenizer {
     token_to_id: HashMap<String, usize>,
     id_to_token: HashMap<usize, String>,
@@ -8,11 +10,11 @@ pub struct Tokenizer {
 }
 
 impl Tokenizer {
-    pub fn new(vocab_file: &str) -> Self {
+    pub fn new(vocab_file: &str) -> Result<Self> {
         let mut token_to_id = HashMap::new();
         let mut id_to_token = HashMap::new();
 
-        let vocab = std::fs::read_to_string(vocab_file).expect("Failed to read vocabulary file");
+        let vocab = std::fs::read_to_string(vocab_file).map_err(|e| Error::io(vocab_file, e))?;
         for (id, token) in vocab.lines().enumerate() {
             token_to_id.insert(token.to_string(), id);
             id_to_token.insert(id, token.to_string());
@@ -26,12 +28,17 @@ impl Tokenizer {
         token_to_id.insert("<pad>".to_string(), pad_id);
         id_to_token.insert(pad_id, "<pad>".to_string());
 
-        Self {
+        Ok(Self {
             token_to_id,
             id_to_token,
             eos_id,
             pad_id,
-        }
+        })
+    }
+
+    /// The number of distinct ids `encode` can produce, including `<eos>` and `<pad>`.
+    pub fn vocab_size(&self) -> usize {
+        self.id_to_token.len()
     }
 
     pub fn encode(&self, text: &str) -> Vec<usize> {
@@ -43,8 +50,7 @@ impl Tokenizer {
 
     pub fn decode(&self, ids: &[usize]) -> String {
         ids.iter()
-            .map(|&id| self.id_to_token.get(&id).unwrap_or(&"<unk>".to_string()))
-            .cloned()
+            .map(|&id| self.id_to_token.get(&id).cloned().unwrap_or_else(|| "<unk>".to_string()))
             .collect::<Vec<String>>()
             .join(" ")
     }
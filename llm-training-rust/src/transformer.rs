@@ -1,16 +1,40 @@
+use serde::{Deserialize, Serialize};
+
 use crate::config::Config;
-use crate::attention::Attention;
+use crate::attention::{Attention, Streaming};
 use crate::feed_forward::FeedForward;
 use crate::layer_norm::LayerNorm;
+use crate::autograd::Var;
+use crate::error::Result;
+use crate::float::Float;
+use crate::module::{prefixed, Module};
+use crate::parameter::Parameter;
+use crate::positional_encoding::PositionScaling;
+use crate::tensor::Tensor;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TransformerLayer<T: Float = f32> {
+    attention: Attention<T>,
+    /// Only in decoder layers: attention over the encoder output between self-attention and the
+    /// feed-forward block, with its own residual connection and layer norm.
+    cross_attention: Option<CrossAttention<T>>,
+    feed_forward: FeedForward<T>,
+    layer_norm1: LayerNorm<T>,
+    layer_norm2: LayerNorm<T>,
+}
 
-pub struct TransformerLayer {
-    attention: Attention,
-    feed_forward: FeedForward,
-    layer_norm1: LayerNorm,
-    layer_norm2: LayerNorm,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct CrossAttention<T: Float> {
+    attention: Attention<T>,
+    layer_norm: LayerNorm<T>,
 }
 
-impl TransformerLayer {
+// An encoder output and the additive mask of its padded positions.
+type Memory<'a, T> = (&'a Var<T>, Option<&'a Tensor<T>>);
+
+impl<T: Float> TransformerLayer<T> {
     pub fn new(config: &Config) -> Self {
         let attention = Attention::new(config);
         let feed_forward = FeedForward::new(config);
@@ -19,77 +43,276 @@ impl TransformerLayer {
 
         Self {
             attention,
+            cross_attention: None,
             feed_forward,
             layer_norm1,
             layer_norm2,
         }
     }
 
-    pub fn forward(&mut self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let attention_output = self.attention.forward(input);
-        let residual1 = input.iter().zip(attention_output.iter()).map(|(x, y)| {
-            x.iter().zip(y.iter()).map(|(&a, &b)| a + b).collect()
-        }).collect::<Vec<Vec<f64>>>();
-        let norm1 = self.layer_norm1.forward(&residual1);
+    /// A decoder layer, which also attends over an encoder output; see `forward_with_memory`.
+    pub fn with_cross_attention(config: &Config) -> Self {
+        let cross_attention = CrossAttention {
+            attention: Attention::cross(config),
+            layer_norm: LayerNorm::new(config.embedding_dim),
+        };
+        Self {
+            cross_attention: Some(cross_attention),
+            ..Self::new(config)
+        }
+    }
+
+    /// Panics for decoder layers, which need `forward_with_memory`.
+    pub fn forward(&mut self, input: &Var<T>, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let attention_output = self.attention.forward(input, mask)?;
+        self.post_attention(input, &attention_output, None, false)
+    }
+
+    /// `forward` for a decoder layer over the `[batch, memory_len, embedding_dim]` encoder output
+    /// `memory`. `memory_mask` must broadcast to `[batch, num_heads, seq, memory_len]`. Layers
+    /// without cross-attention ignore the memory.
+    pub fn forward_with_memory(
+        &mut self,
+        input: &Var<T>,
+        mask: Option<&Tensor<T>>,
+        memory: &Var<T>,
+        memory_mask: Option<&Tensor<T>>,
+    ) -> Result<Var<T>> {
+        let attention_output = self.attention.forward(input, mask)?;
+        self.post_attention(input, &attention_output, Some((memory, memory_mask)), false)
+    }
+
+    /// `forward` for the next positions only, attending over the layer's KV cache.
+    pub fn forward_step(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        let attention_output = self.attention.forward_step(input)?;
+        self.post_attention(input, &attention_output, None, true)
+    }
+
+    /// `forward_with_memory` for the next positions only. The memory's keys and values are
+    /// cached on the first step, so it must not change until `clear_cache`.
+    pub fn forward_step_with_memory(&mut self, input: &Var<T>, memory: &Var<T>, memory_mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let attention_output = self.attention.forward_step(input)?;
+        self.post_attention(input, &attention_output, Some((memory, memory_mask)), true)
+    }
+
+    pub fn attention(&self) -> &Attention<T> {
+        &self.attention
+    }
+
+    pub fn cross_attention(&self) -> Option<&Attention<T>> {
+        self.cross_attention.as_ref().map(|cross| &cross.attention)
+    }
+
+    pub fn feed_forward(&self) -> &FeedForward<T> {
+        &self.feed_forward
+    }
+
+    /// The position the next `forward_step` token takes.
+    pub fn position(&self) -> usize {
+        self.attention.next_position()
+    }
+
+    pub fn set_streaming(&mut self, streaming: Option<Streaming>) {
+        self.attention.set_streaming(streaming);
+    }
 
-        let feed_forward_output = self.feed_forward.forward(&norm1);
-        let residual2 = norm1.iter().zip(feed_forward_output.iter()).map(|(x, y)| {
-            x.iter().zip(y.iter()).map(|(&a, &b)| a + b).collect()
-        }).collect::<Vec<Vec<f64>>>();
-        let norm2 = self.layer_norm2.forward(&residual2);
+    pub fn set_position_scaling(&mut self, scaling: Option<PositionScaling>) {
+        self.attention.set_position_scaling(scaling);
+    }
 
-        norm2
+    pub fn clear_cache(&mut self) {
+        self.attention.clear_cache();
+        if let Some(cross) = &mut self.cross_attention {
+            cross.attention.clear_cache();
+        }
     }
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let grad_norm2 = self.layer_norm2.backward(grad_output, &self.feed_forward.output);
-        let (grad_norm1, grad_feed_forward_output) = grad_norm2.iter().zip(self.feed_forward.output.iter())
-            .map(|(x, y)| (x.clone(), x.clone()))
-            .unzip();
+    // The residual connections, layer norms, cross-attention and feed-forward block that follow
+    // self-attention.
+    fn post_attention(&mut self, input: &Var<T>, attention_output: &Var<T>, memory: Option<Memory<T>>, step: bool) -> Result<Var<T>> {
+        let residual1 = input.add(attention_output);
+        let mut norm1 = self.layer_norm1.forward(&residual1)?;
+
+        if let Some(cross) = &mut self.cross_attention {
+            let (memory, memory_mask) = memory.expect("decoder layers need the encoder output; use forward_with_memory");
+            let cross_output = if step {
+                cross.attention.forward_cross_step(&norm1, memory, memory_mask)?
+            } else {
+                cross.attention.forward_cross(&norm1, memory, memory_mask)?
+            };
+            norm1 = cross.layer_norm.forward(&norm1.add(&cross_output))?;
+        }
+
+        let feed_forward_output = self.feed_forward.forward(&norm1)?;
+        let residual2 = norm1.add(&feed_forward_output);
+        self.layer_norm2.forward(&residual2)
+    }
+}
 
-        let grad_feed_forward = self.feed_forward.backward(&grad_feed_forward_output);
-        let grad_residual1 = self.layer_norm1.backward(&grad_norm1, &self.attention.output);
-        let (grad_input, grad_attention_output) = grad_residual1.iter().zip(self.attention.output.iter())
-            .map(|(x, y)| (x.clone(), x.clone()))
-            .unzip();
+impl<T: Float> Module<T> for TransformerLayer<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("attention", self.attention.named_parameters()));
+        if let Some(cross) = &self.cross_attention {
+            params.extend(prefixed("cross_attention", cross.attention.named_parameters()));
+            params.extend(prefixed("layer_norm_cross", cross.layer_norm.named_parameters()));
+        }
+        params.extend(prefixed("feed_forward", self.feed_forward.named_parameters()));
+        params.extend(prefixed("layer_norm1", self.layer_norm1.named_parameters()));
+        params.extend(prefixed("layer_norm2", self.layer_norm2.named_parameters()));
+        params
+    }
 
-        let grad_attention = self.attention.backward(&grad_attention_output);
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        params.extend(prefixed("attention", self.attention.named_parameters_mut()));
+        if let Some(cross) = &mut self.cross_attention {
+            params.extend(prefixed("cross_attention", cross.attention.named_parameters_mut()));
+            params.extend(prefixed("layer_norm_cross", cross.layer_norm.named_parameters_mut()));
+        }
+        params.extend(prefixed("feed_forward", self.feed_forward.named_parameters_mut()));
+        params.extend(prefixed("layer_norm1", self.layer_norm1.named_parameters_mut()));
+        params.extend(prefixed("layer_norm2", self.layer_norm2.named_parameters_mut()));
+        params
+    }
 
-        grad_input.iter().zip(grad_attention.iter())
-            .map(|(x, y)| x.iter().zip(y.iter()).map(|(&a, &b)| a + b).collect())
-            .collect()
+    fn set_training(&mut self, training: bool) {
+        self.attention.set_training(training);
+        if let Some(cross) = &mut self.cross_attention {
+            cross.attention.set_training(training);
+        }
+        self.feed_forward.set_training(training);
     }
 }
 
-pub struct Transformer {
-    layers: Vec<TransformerLayer>,
-    pub output: Vec<Vec<f64>>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Transformer<T: Float = f32> {
+    layers: Vec<TransformerLayer<T>>,
+    /// The mode last set through `Module::set_training`, kept here so it survives a stack with
+    /// no layers.
+    training: bool,
 }
 
-impl Transformer {
+impl<T: Float> Transformer<T> {
     pub fn new(config: &Config) -> Self {
+        Self::build(config, TransformerLayer::new)
+    }
+
+    /// The decoder of an encoder-decoder model, whose layers all attend over the encoder output.
+    pub fn decoder(config: &Config) -> Self {
+        Self::build(config, TransformerLayer::with_cross_attention)
+    }
+
+    fn build(config: &Config, new_layer: fn(&Config) -> TransformerLayer<T>) -> Self {
         let layers = (0..config.num_layers)
-            .map(|_| TransformerLayer::new(config))
+            .map(|i| {
+                new_layer(&Config {
+                    attention_window: config.layer_attention_window(i),
+                    ..config.clone()
+                })
+            })
             .collect();
-        let output = Vec::new();
 
-        Self { layers, output }
+        Self { layers, training: true }
+    }
+
+    pub fn forward(&mut self, input: &Var<T>, mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let mut output = input.clone();
+        for layer in &mut self.layers {
+            output = layer.forward(&output, mask)?;
+        }
+        Ok(output)
+    }
+
+    /// `forward` for a decoder; see `TransformerLayer::forward_with_memory`.
+    pub fn forward_with_memory(
+        &mut self,
+        input: &Var<T>,
+        mask: Option<&Tensor<T>>,
+        memory: &Var<T>,
+        memory_mask: Option<&Tensor<T>>,
+    ) -> Result<Var<T>> {
+        let mut output = input.clone();
+        for layer in &mut self.layers {
+            output = layer.forward_with_memory(&output, mask, memory, memory_mask)?;
+        }
+        Ok(output)
+    }
+
+    /// `forward` for the next positions only; see `Attention::forward_step`.
+    pub fn forward_step(&mut self, input: &Var<T>) -> Result<Var<T>> {
+        let mut output = input.clone();
+        for layer in &mut self.layers {
+            output = layer.forward_step(&output)?;
+        }
+        Ok(output)
+    }
+
+    /// `forward_with_memory` for the next positions only.
+    pub fn forward_step_with_memory(&mut self, input: &Var<T>, memory: &Var<T>, memory_mask: Option<&Tensor<T>>) -> Result<Var<T>> {
+        let mut output = input.clone();
+        for layer in &mut self.layers {
+            output = layer.forward_step_with_memory(&output, memory, memory_mask)?;
+        }
+        Ok(output)
+    }
+
+    pub fn layers(&self) -> &[TransformerLayer<T>] {
+        &self.layers
+    }
+
+    /// Whether the stack is in training mode; see `Module::train`.
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    /// The position the next `forward_step` token takes, which every layer keeps in step even
+    /// when windowed layers hold fewer keys.
+    pub fn position(&self) -> usize {
+        self.layers.first().map_or(0, |layer| layer.position())
+    }
+
+    pub fn clear_cache(&mut self) {
+        for layer in &mut self.layers {
+            layer.clear_cache();
+        }
+    }
+
+    pub fn set_streaming(&mut self, streaming: Option<Streaming>) {
+        for layer in &mut self.layers {
+            layer.set_streaming(streaming);
+        }
     }
 
-    pub fn forward(&mut self, input: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let mut output = input.to_vec();
+    pub fn set_position_scaling(&mut self, scaling: Option<PositionScaling>) {
         for layer in &mut self.layers {
-            output = layer.forward(&output);
+            layer.set_position_scaling(scaling);
+        }
+    }
+}
+
+impl<T: Float> Module<T> for Transformer<T> {
+    fn named_parameters(&self) -> Vec<(String, &Parameter<T>)> {
+        let mut params = Vec::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            params.extend(prefixed(&format!("layers.{}", i), layer.named_parameters()));
         }
-        self.output = output.clone();
-        output
+        params
     }
 
-    pub fn backward(&mut self, grad_output: &[Vec<f64>]) -> Vec<Vec<f64>> {
-        let mut grad_input = grad_output.to_vec();
-        for layer in self.layers.iter_mut().rev() {
-            grad_input = layer.backward(&grad_input);
+    fn named_parameters_mut(&mut self) -> Vec<(String, &mut Parameter<T>)> {
+        let mut params = Vec::new();
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            params.extend(prefixed(&format!("layers.{}", i), layer.named_parameters_mut()));
         }
-        grad_input
+        params
     }
-}
\ No newline at end of file
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for layer in &mut self.layers {
+            layer.set_training(training);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use llm_training_rust::activation::Activation;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::gelu::erf;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+const ALL: [Activation; 5] = [
+    Activation::Gelu,
+    Activation::GeluTanh,
+    Activation::Silu,
+    Activation::Relu,
+    Activation::ReluSquared,
+];
+
+#[test]
+fn test_erf_known_values() {
+    assert_eq!(erf(0.0), 0.0);
+    assert_abs_diff_eq!(erf(0.5), 0.520_499_877_813_046_5, epsilon = 1e-15);
+    assert_abs_diff_eq!(erf(1.0), 0.842_700_792_949_714_9, epsilon = 1e-15);
+    assert_abs_diff_eq!(erf(2.0), 0.995_322_265_018_952_7, epsilon = 1e-15);
+    assert_abs_diff_eq!(erf(-3.0), -0.999_977_909_503_001_4, epsilon = 1e-15);
+    assert_eq!(erf(7.0), 1.0);
+}
+
+#[test]
+fn test_activation_known_values() {
+    // Exact GELU is x * Phi(x); the tanh form differs from it by up to about 1e-3.
+    assert_abs_diff_eq!(Activation::Gelu.apply(1.0), 0.841_344_746_068_543, epsilon = 1e-12);
+    assert_abs_diff_eq!(Activation::Gelu.apply(-1.0), -0.158_655_253_931_457, epsilon = 1e-12);
+    assert_abs_diff_eq!(Activation::GeluTanh.apply(1.0), 0.841_191_990_607_477, epsilon = 1e-12);
+    assert_abs_diff_eq!(Activation::Silu.apply(1.0), 0.731_058_578_630_005, epsilon = 1e-12);
+    assert_eq!(Activation::Relu.apply(-2.0), 0.0);
+    assert_eq!(Activation::Relu.apply(3.0), 3.0);
+    assert_eq!(Activation::ReluSquared.apply(-2.0), 0.0);
+    assert_eq!(Activation::ReluSquared.apply(3.0), 9.0);
+    for activation in ALL {
+        assert_eq!(activation.apply(0.0), 0.0, "{:?}", activation);
+    }
+}
+
+#[test]
+fn test_activation_derivatives() {
+    // Each derivative matches a central difference of its forward pass away from ReLU's kink.
+    let eps = 1e-6;
+    for activation in ALL {
+        for &x in &[-4.0, -2.5, -1.0, -0.3, 0.2, 0.7, 1.5, 3.0, 5.0] {
+            let numerical = (activation.apply(x + eps) - activation.apply(x - eps)) / (2.0 * eps);
+            assert_abs_diff_eq!(activation.derivative(x), numerical, epsilon = 1e-8);
+        }
+    }
+    assert_eq!(Activation::Relu.derivative(0.0), 0.0);
+    assert_eq!(Activation::ReluSquared.derivative(0.0), 0.0);
+    assert_abs_diff_eq!(Activation::Gelu.derivative(0.0), 0.5, epsilon = 1e-15);
+}
+
+#[test]
+fn test_activation_forward_backward() {
+    let input = Var::leaf(Tensor::new(vec![-1.0, 0.5, 2.0], &[3]));
+    for activation in ALL {
+        input.zero_grad();
+        let output = activation.forward(&input);
+        let expected = [-1.0, 0.5, 2.0].map(|x| activation.apply(x));
+        assert_eq!(output.value().to_vec(), expected);
+
+        output.sum().backward();
+        let expected = [-1.0, 0.5, 2.0].map(|x| activation.derivative(x));
+        assert_eq!(input.grad().unwrap().to_vec(), expected);
+    }
+}
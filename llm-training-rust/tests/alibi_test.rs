@@ -0,0 +1,78 @@
+use llm_training_rust::alibi::{alibi_bias, alibi_slopes};
+use llm_training_rust::config::Config;
+use llm_training_rust::model::Model;
+use llm_training_rust::module::Module;
+use llm_training_rust::optimizer::AdamOptimizer;
+use llm_training_rust::positional_encoding::PositionEncoding;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_alibi_slopes() {
+    assert_eq!(alibi_slopes(4), vec![0.25, 0.0625, 0.015625, 0.00390625]);
+
+    let eight = alibi_slopes(8);
+    assert_eq!(eight[0], 0.5);
+    assert_eq!(eight[7], 2f64.powi(-8));
+
+    // Six heads: the four-head slopes, then every other eight-head slope.
+    let six = alibi_slopes(6);
+    assert_eq!(six[..4], alibi_slopes(4)[..]);
+    assert_eq!(six[4..], [eight[0], eight[2]]);
+}
+
+#[test]
+fn test_alibi_bias() {
+    let bias: Tensor<f64> = alibi_bias(&[0.5, 0.25], 2, 1);
+    assert_eq!(bias.shape(), [2, 2, 3]);
+    assert_eq!(bias.to_vec(), vec![-0.5, 0.0, -0.5, -1.0, -0.5, 0.0, -0.25, 0.0, -0.25, -0.5, -0.25, 0.0]);
+}
+
+// Repeats `1 2 1 3`, so the token after `1` depends on the one before it.
+fn pattern_batch(batch_size: usize, seq_len: usize) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    let token = |i: usize| [1, 2, 1, 3][i % 4];
+    let input = (0..batch_size).map(|b| (0..seq_len).map(|i| token(b + i)).collect()).collect();
+    let target = (0..batch_size).map(|b| (0..seq_len).map(|i| token(b + i + 1)).collect()).collect();
+    (input, target)
+}
+
+// Trains on length-8 windows and returns the perplexity at that length and at four times it.
+fn train_and_extrapolate(position_encoding: PositionEncoding) -> (f64, f64) {
+    let config = Config {
+        vocab_size: 5,
+        max_seq_len: 8,
+        embedding_dim: 16,
+        num_layers: 1,
+        num_heads: 2,
+        feed_forward_dim: 32,
+        dropout_rate: 0.0,
+        position_encoding,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    let mut optimizer = AdamOptimizer::new(0.01);
+
+    let (input, target) = pattern_batch(4, config.max_seq_len);
+    for _ in 0..150 {
+        model.zero_grad();
+        let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+        loss.unwrap().backward();
+        optimizer.step(&mut model);
+    }
+    let trained = model.perplexity(&input, &target).unwrap();
+
+    model.set_max_seq_len(4 * config.max_seq_len);
+    let (long_input, long_target) = pattern_batch(4, 4 * config.max_seq_len);
+    (trained, model.perplexity(&long_input, &long_target).unwrap())
+}
+
+#[test]
+fn test_alibi_perplexity_is_stable_past_training_length() {
+    let (trained, extrapolated) = train_and_extrapolate(PositionEncoding::Alibi);
+    assert!(trained < 1.2, "training did not converge: perplexity {}", trained);
+    assert_abs_diff_eq!(extrapolated, trained, epsilon = 0.1);
+
+    // Sinusoidal encodings meet unseen position vectors past the training length.
+    let (_, sinusoidal) = train_and_extrapolate(PositionEncoding::Sinusoidal);
+    assert!(sinusoidal > extrapolated, "sinusoidal {} vs alibi {}", sinusoidal, extrapolated);
+}
@@ -1,7 +1,37 @@
-use llm_training_rust::attention::Attention;
+use llm_training_rust::attention::{window_mask, Attention, Streaming};
 use llm_training_rust::config::Config;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::init::Init;
+use llm_training_rust::module::Module;
+use llm_training_rust::positional_encoding::PositionEncoding;
+use llm_training_rust::tensor::Tensor;
 use approx::assert_abs_diff_eq;
 
+// Identity projections with zero bias, so queries, keys and values all equal the input.
+fn identity_attention(embedding_dim: usize, num_heads: usize) -> Attention<f64> {
+    let config = Config {
+        embedding_dim,
+        num_heads,
+        dropout_rate: 0.0,
+        causal: false,
+        ..Default::default()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    for (name, param) in attention.named_parameters_mut() {
+        let shape = param.shape();
+        *param.value_mut() = if name.ends_with("weight") {
+            let mut eye = Tensor::zeros(&shape);
+            for i in 0..shape[0] {
+                eye.set(&[i, i], 1.0);
+            }
+            eye
+        } else {
+            Tensor::zeros(&shape)
+        };
+    }
+    attention
+}
+
 #[test]
 fn test_attention_forward() {
     let config = Config {
@@ -16,15 +46,15 @@ fn test_attention_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut attention = Attention::new(&config);
+    let mut attention: Attention = Attention::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = attention.forward(&input);
+    let input = Var::constant(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = attention.forward(&input, None).unwrap();
 
-    assert_eq!(output.len(), config.max_seq_len);
-    assert_eq!(output[0].len(), config.embedding_dim);
+    assert_eq!(output.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
 }
 
 #[test]
@@ -41,16 +71,269 @@ fn test_attention_backward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
+    };
+
+    let mut attention: Attention = Attention::new(&config);
+
+    let input = Var::leaf(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = attention.forward(&input, None).unwrap();
+
+    let grad_output = Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]);
+    output.backward_with(grad_output);
+    let grad_input = input.grad().unwrap();
+
+    assert_eq!(grad_input.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
+}
+
+#[test]
+fn test_attention_heads_have_independent_softmax() {
+    let (batch_size, seq_len, embedding_dim, num_heads) = (2, 3, 6, 3);
+    let head_dim = embedding_dim / num_heads;
+    let mut attention = identity_attention(embedding_dim, num_heads);
+    assert_eq!(attention.head_dim(), head_dim);
+
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[batch_size, seq_len, embedding_dim], 1, 1);
+    let output = attention.forward(&Var::constant(x.clone()), None).unwrap();
+
+    // Reference: each head attends over its own slice of the features, scaled by sqrt(head_dim).
+    let heads = (0..num_heads)
+        .map(|h| {
+            let x_h = x.narrow(2, h * head_dim, head_dim);
+            let scores = x_h.matmul(&x_h.transpose(1, 2)).scale(1.0 / (head_dim as f64).sqrt());
+            scores.softmax(2).matmul(&x_h)
+        })
+        .collect::<Vec<_>>();
+    let expected = Tensor::cat(&heads.iter().collect::<Vec<_>>(), 2);
+
+    for (a, b) in output.value().to_vec().iter().zip(expected.to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+    }
+
+    let weights = attention.attention_weights.clone().unwrap();
+    assert_eq!(weights.shape(), [batch_size, num_heads, seq_len, seq_len]);
+    for row in weights.sum_axis(3, false).to_vec() {
+        assert_abs_diff_eq!(row, 1.0, epsilon = 1e-12);
+    }
+}
+
+#[test]
+fn test_causal_attention_ignores_later_positions() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        ..Default::default()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    attention.eval();
+    assert!(attention.is_causal());
+
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 5, 8], 1, 1);
+    let output = attention.forward(&Var::constant(x.clone()), None).unwrap().value().clone();
+
+    let weights = attention.attention_weights.clone().unwrap();
+    for i in 0..5 {
+        for j in i + 1..5 {
+            assert_eq!(weights.get(&[0, 0, i, j]), 0.0);
+            assert_eq!(weights.get(&[0, 1, i, j]), 0.0);
+        }
+    }
+
+    // Changing the last two positions leaves the first three outputs untouched.
+    let changed = Tensor::cat(&[&x.narrow(1, 0, 3), &Init::Normal(1.0).tensor(&[1, 2, 8], 1, 1)], 1);
+    let changed_output = attention.forward(&Var::constant(changed), None).unwrap().value().clone();
+    assert_eq!(output.narrow(1, 0, 3), changed_output.narrow(1, 0, 3));
+    assert_ne!(output.narrow(1, 3, 2), changed_output.narrow(1, 3, 2));
+}
+
+#[test]
+fn test_forward_step_matches_forward() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        ..Default::default()
     };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    attention.eval();
 
-    let mut attention = Attention::new(&config);
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[2, 5, 8], 1, 1);
+    let full = attention.forward(&Var::constant(x.clone()), None).unwrap().value().clone();
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = attention.forward(&input);
+    // A three-position prefill followed by two single-position steps.
+    let mut steps = Vec::new();
+    for (start, len) in [(0, 3), (3, 1), (4, 1)] {
+        let step = attention.forward_step(&Var::constant(x.narrow(1, start, len))).unwrap();
+        steps.push(step.value().clone());
+    }
+    assert_eq!(attention.kv_cache().len(), 5);
+    assert_eq!(attention.kv_cache().keys().unwrap().shape(), [2, 2, 5, 4]);
 
-    let grad_output = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let grad_input = attention.backward(&grad_output);
+    let incremental = Tensor::cat(&steps.iter().collect::<Vec<_>>(), 1);
+    for (a, b) in full.to_vec().iter().zip(incremental.to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+    }
+
+    attention.clear_cache();
+    assert!(attention.kv_cache().is_empty());
+}
+
+// Copies `grouped` into a full multi-head attention, repeating each key/value head's columns for
+// every query head that shares it.
+fn expand_kv_heads(grouped: &Attention<f64>, config: &Config) -> Attention<f64> {
+    let head_dim = grouped.head_dim();
+    let group = grouped.num_heads() / grouped.num_kv_heads();
+    let mut full: Attention<f64> = Attention::new(&Config {
+        num_kv_heads: None,
+        ..config.clone()
+    });
+    let sources = grouped.named_parameters();
+    for (name, param) in full.named_parameters_mut() {
+        let source = sources.iter().find(|(n, _)| *n == name).unwrap().1.value().clone();
+        *param.value_mut() = if name.starts_with("key_matrix") || name.starts_with("value_matrix") {
+            let axis = source.ndim() - 1;
+            let heads = (0..grouped.num_heads())
+                .map(|h| source.narrow(axis, h / group * head_dim, head_dim))
+                .collect::<Vec<_>>();
+            Tensor::cat(&heads.iter().collect::<Vec<_>>(), axis)
+        } else {
+            source
+        };
+    }
+    full
+}
 
-    assert_eq!(grad_input.len(), config.max_seq_len);
-    assert_eq!(grad_input[0].len(), config.embedding_dim);
-}
\ No newline at end of file
+#[test]
+fn test_grouped_query_attention_matches_expanded_heads() {
+    for num_kv_heads in [2, 1] {
+        let config = Config {
+            embedding_dim: 8,
+            num_heads: 4,
+            num_kv_heads: Some(num_kv_heads),
+            dropout_rate: 0.0,
+            ..Default::default()
+        };
+        let mut grouped: Attention<f64> = Attention::new(&config);
+        assert_eq!(grouped.num_kv_heads(), num_kv_heads);
+        let key_weight = grouped.named_parameters().into_iter().find(|(n, _)| n == "key_matrix.weight").unwrap().1;
+        assert_eq!(key_weight.shape(), [8, num_kv_heads * 2]);
+        let mut full = expand_kv_heads(&grouped, &config);
+
+        let x: Tensor<f64> = Init::Normal(1.0).tensor(&[2, 5, 8], 1, 1);
+        let expected = full.forward(&Var::constant(x.clone()), None).unwrap();
+        let output = grouped.forward(&Var::constant(x.clone()), None).unwrap();
+        for (a, b) in output.value().to_vec().iter().zip(expected.value().to_vec()) {
+            assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+        }
+
+        // The cache only holds the shared heads.
+        grouped.forward_step(&Var::constant(x)).unwrap();
+        assert_eq!(grouped.kv_cache().keys().unwrap().shape(), [2, num_kv_heads, 5, 2]);
+    }
+}
+
+#[test]
+fn test_window_mask() {
+    let causal: Tensor<f64> = window_mask(4, 2, true);
+    let bidirectional: Tensor<f64> = window_mask(4, 2, false);
+    let inf = f64::NEG_INFINITY;
+
+    assert_eq!(causal.to_vec(), vec![0.0, inf, inf, inf, 0.0, 0.0, inf, inf, inf, 0.0, 0.0, inf, inf, inf, 0.0, 0.0]);
+    assert_eq!(bidirectional.to_vec(), vec![0.0, 0.0, inf, inf, 0.0, 0.0, 0.0, inf, inf, 0.0, 0.0, 0.0, inf, inf, 0.0, 0.0]);
+}
+
+#[test]
+fn test_sliding_window_attention_and_cache_eviction() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        attention_window: Some(3),
+        ..Default::default()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    attention.eval();
+
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 6, 8], 1, 1);
+    let full = attention.forward(&Var::constant(x.clone()), None).unwrap().value().clone();
+
+    // Position 0 is out of reach from position 3 onwards.
+    let changed = Tensor::cat(&[&Init::Normal(1.0).tensor(&[1, 1, 8], 1, 1), &x.narrow(1, 1, 5)], 1);
+    let changed_output = attention.forward(&Var::constant(changed), None).unwrap().value().clone();
+    assert_ne!(full.narrow(1, 2, 1), changed_output.narrow(1, 2, 1));
+    assert_eq!(full.narrow(1, 3, 3), changed_output.narrow(1, 3, 3));
+
+    // Decoding keeps only the `window - 1` keys later queries still need.
+    attention.forward_step(&Var::constant(x.narrow(1, 0, 4))).unwrap();
+    assert_eq!((attention.kv_cache().evicted(), attention.kv_cache().len()), (2, 2));
+    for i in 4..6 {
+        let step = attention.forward_step(&Var::constant(x.narrow(1, i, 1))).unwrap().value().clone();
+        for (a, b) in step.to_vec().iter().zip(full.narrow(1, i, 1).to_vec()) {
+            assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+        }
+        assert_eq!(attention.kv_cache().len(), 2);
+    }
+    assert_eq!(attention.kv_cache().position(), 6);
+}
+
+#[test]
+fn test_streaming_attention_keeps_sinks_and_recent_positions() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        position_encoding: PositionEncoding::Rope,
+        ..Default::default()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    attention.eval();
+    attention.set_streaming(Some(Streaming { num_sinks: 2, window: 3 }));
+
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 9, 8], 1, 1);
+    for t in 0..9 {
+        let step = attention.forward_step(&Var::constant(x.narrow(1, t, 1))).unwrap().value().clone();
+
+        // The same attention over the two sinks and the last three tokens, at positions 0..5.
+        let kept = (0..t + 1).filter(|&i| i < 2 || i + 3 > t).map(|i| x.narrow(1, i, 1)).collect::<Vec<_>>();
+        let kept = Tensor::cat(&kept.iter().collect::<Vec<_>>(), 1);
+        let expected = attention.forward(&Var::constant(kept.clone()), None).unwrap().value().clone();
+        for (a, b) in step.to_vec().iter().zip(expected.narrow(1, kept.dim(1) - 1, 1).to_vec()) {
+            assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+        }
+        assert!(attention.kv_cache().len() <= 4);
+        assert_eq!(attention.next_position(), attention.kv_cache().len());
+    }
+    assert_eq!(attention.kv_cache().evicted(), 5);
+}
+
+#[test]
+fn test_cross_attention_reads_keys_from_memory() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        position_encoding: PositionEncoding::Rope,
+        attention_window: Some(1),
+        ..Default::default()
+    };
+    let mut attention: Attention<f64> = Attention::cross(&config);
+    attention.eval();
+    assert!(!attention.is_causal() && attention.window().is_none() && attention.rotary().is_none());
+
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[2, 3, 8], 1, 1);
+    let memory: Tensor<f64> = Init::Normal(1.0).tensor(&[2, 5, 8], 1, 1);
+    let output = attention.forward_cross(&Var::constant(x.clone()), &Var::constant(memory.clone()), None).unwrap();
+    assert_eq!(output.shape(), [2, 3, 8]);
+    assert_eq!(attention.attention_weights.as_ref().unwrap().shape(), [2, 2, 3, 5]);
+
+    // Decoding step by step caches the memory once and matches the full pass row by row.
+    let mut steps = Vec::new();
+    for i in 0..3 {
+        let step = attention.forward_cross_step(&Var::constant(x.narrow(1, i, 1)), &Var::constant(memory.clone()), None);
+        steps.push(step.unwrap().value().clone());
+        assert_eq!(attention.kv_cache().len(), 5);
+    }
+    let incremental = Tensor::cat(&steps.iter().collect::<Vec<_>>(), 1);
+    for (a, b) in output.value().to_vec().iter().zip(incremental.to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+    }
+
+    let wrong_batch: Tensor<f64> = Tensor::zeros(&[3, 5, 8]);
+    assert!(attention.forward_cross(&Var::constant(x), &Var::constant(wrong_batch), None).is_err());
+}
@@ -0,0 +1,69 @@
+use llm_training_rust::autograd::Var;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_autograd_matmul_and_broadcast_add() {
+    let x = Var::leaf(Tensor::new(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]));
+    let w = Var::leaf(Tensor::new(vec![5.0, 6.0, 7.0, 8.0], &[2, 2]));
+    let b = Var::leaf(Tensor::new(vec![1.0, -1.0], &[2]));
+
+    x.matmul(&w).add(&b).sum().backward();
+
+    // d/dx sum(xw + b) = 1 w^T, d/dw = x^T 1, d/db = number of rows
+    assert_eq!(x.grad().unwrap().to_vec(), vec![11.0, 15.0, 11.0, 15.0]);
+    assert_eq!(w.grad().unwrap().to_vec(), vec![4.0, 4.0, 6.0, 6.0]);
+    assert_eq!(b.grad().unwrap().to_vec(), vec![2.0, 2.0]);
+}
+
+#[test]
+fn test_autograd_accumulates_reused_values() {
+    let x = Var::leaf(Tensor::new(vec![3.0], &[1]));
+    let y = x.mul(&x).add(&x);
+    y.backward();
+    assert_eq!(x.grad().unwrap().to_vec(), vec![7.0]);
+
+    // Leaf gradients accumulate across backward calls until cleared.
+    y.backward();
+    assert_eq!(x.grad().unwrap().to_vec(), vec![14.0]);
+    x.zero_grad();
+    assert!(x.grad().is_none());
+}
+
+#[test]
+fn test_autograd_softmax_cross_entropy() {
+    let logits = Var::leaf(Tensor::new(vec![1.0, 2.0, 3.0, 0.5, 0.5, 0.5], &[2, 3]));
+    let loss = logits.log_softmax(1).gather_last(&[2, 0]).sum().scale(-0.5);
+    loss.backward();
+
+    // The gradient of mean cross-entropy is (softmax - one_hot) / rows.
+    let probs = logits.value().softmax(1);
+    let grad = logits.grad().unwrap();
+    for (row, target) in [(0, 2), (1, 0)] {
+        for col in 0..3 {
+            let one_hot = if col == target { 1.0 } else { 0.0 };
+            assert_abs_diff_eq!(grad.get(&[row, col]), (probs.get(&[row, col]) - one_hot) / 2.0, epsilon = 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_autograd_constants_are_not_differentiated() {
+    let x = Var::constant(Tensor::ones(&[2, 2]));
+    let w = Var::leaf(Tensor::ones(&[2, 2]));
+    let y = x.matmul(&w).softmax(1).narrow(0, 1, 1);
+    y.backward_with(Tensor::new(vec![1.0, 0.0], &[1, 2]));
+
+    assert!(x.grad().is_none());
+    let grad = w.grad().unwrap();
+    assert_eq!(grad.shape(), &[2, 2]);
+    assert_abs_diff_eq!(grad.get(&[0, 0]), 0.25, epsilon = 1e-12);
+    assert_abs_diff_eq!(grad.get(&[0, 1]), -0.25, epsilon = 1e-12);
+}
+
+#[test]
+fn test_autograd_select_rows_scatters_gradients() {
+    let table: Var = Var::leaf(Tensor::zeros(&[4, 2]));
+    table.select_rows(&[1, 3, 1]).sum().backward();
+    assert_eq!(table.grad().unwrap().to_vec(), vec![0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 1.0, 1.0]);
+}
@@ -0,0 +1,11 @@
+// Helpers shared by the integration tests; each test file includes them with `mod common;`.
+
+/// Writes `contents` to `name` in a temporary directory of the calling test crate, so test
+/// files running in parallel never share a fixture, and returns its path.
+pub fn write_fixture(name: &str, contents: &str) -> String {
+    let dir = std::env::temp_dir().join(concat!("llm_training_rust_", env!("CARGO_CRATE_NAME")));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
@@ -0,0 +1,175 @@
+use llm_training_rust::activation::Activation;
+use llm_training_rust::attention::Streaming;
+use llm_training_rust::config::Config;
+use llm_training_rust::data_loader::DataLoader;
+use llm_training_rust::error::Error;
+use llm_training_rust::feed_forward::FfnType;
+use llm_training_rust::positional_encoding::{PositionEncoding, PositionScaling};
+use llm_training_rust::tokenizer::Tokenizer;
+
+fn scratch_path(name: &str) -> String {
+    let dir = std::env::temp_dir().join("llm_training_rust_config_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+fn assert_invalid(config: &Config, needle: &str) {
+    match config.validate() {
+        Err(Error::InvalidConfig(message)) => assert!(message.contains(needle), "{}", message),
+        other => panic!("expected an invalid config mentioning {:?}, got {:?}", needle, other),
+    }
+}
+
+#[test]
+fn test_config_from_to_json() {
+    let json_str = r#"
+        {
+            "vocab_size": 100,
+            "max_seq_len": 20,
+            "embedding_dim": 32,
+            "num_layers": 2,
+            "num_heads": 4,
+            "feed_forward_dim": 64,
+            "dropout_rate": 0.1,
+            "learning_rate": 0.001,
+            "batch_size": 2,
+            "num_epochs": 1,
+            "checkpoint_interval": 10
+        }
+    "#;
+
+    let config = Config::from_json(json_str).unwrap();
+    let serialized_json = config.to_json();
+
+    assert_eq!(Config::from_json(&serialized_json).unwrap(), config);
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn test_config_save_load_file() {
+    let config = Config {
+        num_heads: 8,
+        seed: 17,
+        ..Default::default()
+    };
+
+    let file_path = scratch_path("config.json");
+
+    config.save_to_file(&file_path).unwrap();
+    let loaded_config = Config::from_file(&file_path).unwrap();
+
+    assert_eq!(config, loaded_config);
+}
+
+#[test]
+fn test_config_rejects_unknown_fields() {
+    let json_str = Config::default().to_json().replace("\"max_seq_len\"", "\"seq_len\"");
+
+    assert!(matches!(Config::from_json(&json_str), Err(Error::Parse { .. })));
+}
+
+#[test]
+fn test_config_validate() {
+    assert!(Config::default().validate().is_ok());
+
+    assert_invalid(&Config { num_heads: 5, ..Default::default() }, "divisible by num_heads");
+    assert_invalid(&Config { num_heads: 0, ..Default::default() }, "num_heads must be positive");
+    assert_invalid(&Config { num_kv_heads: Some(3), ..Default::default() }, "divisible by num_kv_heads");
+    assert_invalid(&Config { num_kv_heads: Some(0), ..Default::default() }, "num_kv_heads must be positive");
+    assert!(Config { num_kv_heads: Some(1), ..Default::default() }.validate().is_ok());
+    assert_invalid(&Config { attention_window: Some(0), ..Default::default() }, "attention_window must be positive");
+    assert_invalid(&Config { global_attention_every: Some(2), ..Default::default() }, "needs an attention_window");
+    let streaming = Some(Streaming { num_sinks: 4, window: 17 });
+    let rope = PositionEncoding::Rope;
+    assert_invalid(&Config { streaming, position_encoding: rope, ..Default::default() }, "num_sinks + window <= max_seq_len");
+    let streaming = Some(Streaming { num_sinks: 4, window: 12 });
+    assert!(Config { streaming, position_encoding: rope, ..Default::default() }.validate().is_ok());
+    assert!(Config { streaming, position_encoding: PositionEncoding::Alibi, ..Default::default() }.validate().is_ok());
+    assert_invalid(&Config { streaming, ..Default::default() }, "rope or alibi");
+    let learned = PositionEncoding::Learned;
+    assert_invalid(&Config { streaming, position_encoding: learned, ..Default::default() }, "rope or alibi");
+
+    let linear = Some(PositionScaling::Linear { factor: 4.0 });
+    let alibi = PositionEncoding::Alibi;
+    assert_invalid(&Config { position_scaling: linear, position_encoding: alibi, ..Default::default() }, "sinusoidal and rope");
+    let shrink = Some(PositionScaling::Ntk { factor: 0.5 });
+    assert_invalid(&Config { position_scaling: shrink, ..Default::default() }, "at least 1");
+    let yarn = Some(PositionScaling::Yarn { factor: 2.0, original_max_seq_len: 0 });
+    assert_invalid(&Config { position_scaling: yarn, ..Default::default() }, "original_max_seq_len");
+    assert_invalid(&Config { dropout_rate: 1.0, ..Default::default() }, "dropout_rate");
+    assert_invalid(&Config { learning_rate: -0.1, ..Default::default() }, "learning_rate");
+    assert_invalid(&Config { checkpoint_interval: 0, ..Default::default() }, "checkpoint_interval");
+}
+
+#[test]
+fn test_config_validate_with_tokenizer_and_data() {
+    let vocab_file = scratch_path("vocab.txt");
+    std::fs::write(&vocab_file, "a\nb\nc\n").unwrap();
+    let data_file = scratch_path("data.txt");
+    std::fs::write(&data_file, "a b c ".repeat(10)).unwrap();
+
+    let tokenizer = Tokenizer::new(&vocab_file).unwrap();
+    let config = Config {
+        vocab_size: 5,
+        max_seq_len: 4,
+        batch_size: 2,
+        embedding_dim: 8,
+        num_heads: 2,
+        ..Default::default()
+    };
+    let data_loader = DataLoader::new(&data_file, config.batch_size, config.max_seq_len, &tokenizer).unwrap();
+    assert!(config.validate_with(&tokenizer, &data_loader).is_ok());
+
+    let wrong_vocab = Config { vocab_size: 100, ..config.clone() };
+    let err = wrong_vocab.validate_with(&tokenizer, &data_loader).unwrap_err();
+    assert!(err.to_string().contains("does not match the tokenizer"), "{}", err);
+
+    let too_big = Config { batch_size: 8, ..config.clone() };
+    let err = too_big.validate_with(&tokenizer, &data_loader).unwrap_err();
+    assert!(err.to_string().contains("the dataset has 30"), "{}", err);
+}
+
+#[test]
+fn test_position_scaling_config() {
+    let json = Config::default().to_json().replace(
+        "\"position_scaling\": null",
+        "\"position_scaling\": {\"yarn\": {\"factor\": 4.0, \"original_max_seq_len\": 512}}",
+    );
+    let config = Config::from_json(&json).unwrap();
+    assert_eq!(config.position_scaling, Some(PositionScaling::Yarn { factor: 4.0, original_max_seq_len: 512 }));
+    assert!(config.validate().is_ok());
+
+    let typo = json.replace("original_max_seq_len", "original_len");
+    assert!(Config::from_json(&typo).is_err());
+}
+
+#[test]
+fn test_ffn_type_config() {
+    let json = Config::default().to_json();
+    assert!(json.contains("\"ffn_type\": \"mlp\""), "{}", json);
+
+    let config = Config::from_json(&json.replace("\"mlp\"", "\"swiglu\"")).unwrap();
+    assert_eq!(config.ffn_type, FfnType::SwiGlu);
+    let config = Config::from_json(&json.replace("\"mlp\"", "\"geglu\"")).unwrap();
+    assert_eq!(config.ffn_type, FfnType::GeGlu);
+    assert!(Config::from_json(&json.replace("\"mlp\"", "\"glu\"")).is_err());
+}
+
+#[test]
+fn test_activation_config() {
+    let json = Config::default().to_json();
+    assert!(json.contains("\"activation\": null"), "{}", json);
+
+    for (name, activation) in [
+        ("gelu", Activation::Gelu),
+        ("gelu_tanh", Activation::GeluTanh),
+        ("silu", Activation::Silu),
+        ("swish", Activation::Silu),
+        ("relu", Activation::Relu),
+        ("relu_squared", Activation::ReluSquared),
+    ] {
+        let config = Config::from_json(&json.replace("\"activation\": null", &format!("\"activation\": \"{}\"", name))).unwrap();
+        assert_eq!(config.activation, Some(activation));
+    }
+    assert!(Config::from_json(&json.replace("\"activation\": null", "\"activation\": \"tanh\"")).is_err());
+}
@@ -1,6 +1,14 @@
+use llm_training_rust::data_loader::{pad_batch, PairBatch, PairDataLoader};
+use llm_training_rust::error::Error;
 use llm_training_rust::optimizer::AdamOptimizer;
 use llm_training_rust::model::Model;
 use llm_training_rust::config::Config;
+use llm_training_rust::module::Module;
+use llm_training_rust::tokenizer::Tokenizer;
+
+mod common;
+
+use common::write_fixture;
 
 #[test]
 fn test_optimizer_step() {
@@ -16,20 +24,61 @@ fn test_optimizer_step() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut model = Model::new(&config);
+    let mut model: Model = Model::new(&config);
     let mut optimizer = AdamOptimizer::new(config.learning_rate);
 
     // Perform a forward pass and backward pass to compute gradients
     let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
     let target = vec![vec![2, 3, 4, 5], vec![6, 7, 8, 9]];
-    let (logits, _) = model.forward(&input, Some(&target));
-    model.backward(&logits, &input, &target);
+    let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+    loss.unwrap().backward();
+
+    let before = model.parameters().iter().map(|p| p.value().clone()).collect::<Vec<_>>();
 
     // Perform an optimizer step
     optimizer.step(&mut model);
 
-    // Check if the model parameters are updated
-    // You can add more specific assertions based on your implementation
-}
\ No newline at end of file
+    // Every parameter that received a gradient moves
+    for (param, old) in model.parameters().iter().zip(&before) {
+        if param.grad().is_some_and(|g| g.to_vec().iter().any(|&x| x != 0.0)) {
+            assert_ne!(&*param.value(), old, "{} was not updated", param.name());
+        }
+    }
+}
+
+#[test]
+fn test_pad_batch() {
+    let (ids, mask) = pad_batch(&[vec![4, 5, 6], vec![7]], 9);
+
+    assert_eq!(ids, vec![vec![4, 5, 6], vec![7, 9, 9]]);
+    assert_eq!(mask, vec![vec![true, true, true], vec![true, false, false]]);
+}
+
+#[test]
+fn test_pair_data_loader_batches() {
+    // ids: a=0 b=1 c=2 x=3 y=4 z=5 <eos>=6 <pad>=7
+    let tokenizer = Tokenizer::new(&write_fixture("pairs_vocab.txt", "a\nb\nc\nx\ny\nz")).unwrap();
+    let pairs = write_fixture("pairs.txt", "a b c\tx\n\nc\ty z x y\nb\tz\n");
+    let mut data_loader = PairDataLoader::new(&pairs, 2, 3, &tokenizer).unwrap();
+    assert_eq!(data_loader.num_pairs(), 3);
+    assert_eq!(data_loader.len(), 1);
+    assert_eq!(data_loader.pad_id(), 7);
+
+    // Targets are cut to `max_seq_len - 1` tokens before `<eos>` is added.
+    let batch = data_loader.iter().next().unwrap();
+    assert_eq!(
+        batch,
+        PairBatch {
+            source: vec![vec![0, 1, 2], vec![2, 7, 7]],
+            source_mask: vec![vec![true, true, true], vec![true, false, false]],
+            input: vec![vec![6, 3, 7], vec![6, 4, 5]],
+            target: vec![vec![3, 6, 7], vec![4, 5, 6]],
+        }
+    );
+
+    let no_tab = write_fixture("no_tab.txt", "a b\tc\na b c\n");
+    assert!(matches!(PairDataLoader::new(&no_tab, 2, 3, &tokenizer), Err(Error::Parse { .. })));
+}
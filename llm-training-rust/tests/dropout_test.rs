@@ -0,0 +1,40 @@
+use llm_training_rust::autograd::Var;
+use llm_training_rust::dropout::Dropout;
+use llm_training_rust::module::Module;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_dropout_train_mode() {
+    let mut dropout = Dropout::new(0.5);
+    assert!(dropout.is_training());
+
+    let input = Var::leaf(Tensor::ones(&[64, 64]));
+    let output = dropout.forward(&input);
+
+    // Survivors are scaled by 1 / (1 - rate), so the expected value is unchanged.
+    let values = output.value().to_vec();
+    assert!(values.iter().all(|&x| x == 0.0 || x == 2.0));
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    assert_abs_diff_eq!(mean, 1.0, epsilon = 0.1);
+
+    output.backward();
+    let mask = dropout.mask.clone().unwrap();
+    assert_eq!(input.grad().unwrap(), mask);
+}
+
+#[test]
+fn test_dropout_eval_mode() {
+    let mut dropout: Dropout = Dropout::new(0.5);
+    dropout.eval();
+    assert!(!dropout.is_training());
+
+    let input = Var::constant(Tensor::ones(&[4, 4]));
+    let output = dropout.forward(&input);
+
+    assert_eq!(*output.value(), *input.value());
+    assert!(dropout.mask.is_none());
+
+    dropout.train();
+    assert!(dropout.is_training());
+}
@@ -0,0 +1,165 @@
+use llm_training_rust::config::Config;
+use llm_training_rust::data_loader::PairDataLoader;
+use llm_training_rust::encoder_decoder::EncoderDecoderModel;
+use llm_training_rust::error::Error;
+use llm_training_rust::module::Module;
+use llm_training_rust::optimizer::AdamOptimizer;
+use llm_training_rust::tensor::Tensor;
+use llm_training_rust::tokenizer::Tokenizer;
+use approx::assert_abs_diff_eq;
+
+mod common;
+
+use common::write_fixture;
+
+fn config() -> Config {
+    Config {
+        vocab_size: 10,
+        max_seq_len: 6,
+        embedding_dim: 16,
+        num_layers: 2,
+        num_heads: 2,
+        feed_forward_dim: 32,
+        dropout_rate: 0.0,
+        ..Default::default()
+    }
+}
+
+fn assert_close(a: &Tensor<f64>, b: &Tensor<f64>) {
+    assert_eq!(a.shape(), b.shape());
+    for (x, y) in a.to_vec().iter().zip(b.to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 1e-10);
+    }
+}
+
+#[test]
+fn test_encoder_decoder_forward() {
+    let mut model: EncoderDecoderModel = EncoderDecoderModel::new(&config());
+    let source = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+    let input = vec![vec![8, 1, 2], vec![8, 3, 4]];
+    let target = vec![vec![1, 2, 8], vec![3, 4, 8]];
+
+    let (logits, loss) = model.forward(&source, &input, Some(&target), None).unwrap();
+    assert_eq!(logits.shape(), [2, 3, 10]);
+    assert!(loss.unwrap().value().item().is_finite());
+
+    let names = model.named_parameters().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    assert!(names.contains(&"encoder.layers.0.attention.query_matrix.weight".to_string()));
+    assert!(names.contains(&"decoder.layers.1.cross_attention.query_matrix.weight".to_string()));
+    assert!(!names.iter().any(|name| name.starts_with("encoder") && name.contains("cross_attention")));
+
+    // Mismatched source and target batches are rejected.
+    assert!(model.forward(&source, &input[..1], None, None).is_err());
+}
+
+#[test]
+fn test_encoder_is_bidirectional_and_decoder_is_causal() {
+    let mut model: EncoderDecoderModel<f64> = EncoderDecoderModel::new(&config());
+    assert!(!model.encoder().layers()[0].attention().is_causal());
+    assert!(model.decoder().layers()[0].attention().is_causal());
+
+    // Changing the last source token changes the encoding of the first.
+    let memory = model.encode(&[vec![1, 2, 3]], None).unwrap().value().narrow(1, 0, 1);
+    let changed = model.encode(&[vec![1, 2, 4]], None).unwrap().value().narrow(1, 0, 1);
+    assert_ne!(memory, changed);
+
+    // Changing the last decoder input leaves earlier logits alone.
+    let source = vec![vec![1, 2, 3]];
+    let (logits, _) = model.forward(&source, &[vec![8, 4, 5]], None, None).unwrap();
+    let (changed, _) = model.forward(&source, &[vec![8, 4, 6]], None, None).unwrap();
+    assert_close(&logits.value().narrow(1, 0, 2), &changed.value().narrow(1, 0, 2));
+}
+
+#[test]
+fn test_padded_source_matches_unpadded() {
+    let mut model: EncoderDecoderModel<f64> = EncoderDecoderModel::new(&config());
+    let input = vec![vec![8, 4, 5]];
+
+    let (logits, _) = model.forward(&[vec![1, 2]], &input, None, None).unwrap();
+    let source_mask = vec![vec![true, true, false, false]];
+    let (padded, _) = model.forward(&[vec![1, 2, 9, 9]], &input, None, Some(&source_mask)).unwrap();
+    assert_close(&logits.value(), &padded.value());
+}
+
+#[test]
+fn test_encoder_decoder_forward_step_matches_forward() {
+    let mut model: EncoderDecoderModel<f64> = EncoderDecoderModel::new(&config());
+    let source = vec![vec![1, 2, 3, 4], vec![5, 6, 9, 9]];
+    let source_mask = vec![vec![true; 4], vec![true, true, false, false]];
+    let input = vec![vec![8, 1, 2, 3], vec![8, 4, 5, 6]];
+    let (full, _) = model.forward(&source, &input, None, Some(&source_mask)).unwrap();
+
+    let memory = model.encode(&source, Some(&source_mask)).unwrap();
+    let prefill = model.forward_step(&[vec![8, 1], vec![8, 4]], &memory, Some(&source_mask)).unwrap();
+    let step = model.forward_step(&[vec![2], vec![5]], &memory, Some(&source_mask)).unwrap();
+    assert_eq!(model.position(), 3);
+    assert_close(&full.value().narrow(1, 0, 2), &prefill.value());
+    assert_close(&full.value().narrow(1, 2, 1), &step.value());
+
+    model.clear_cache();
+    assert_eq!(model.position(), 0);
+}
+
+#[test]
+fn test_encoder_decoder_learns_from_pairs() {
+    let vocab_file = write_fixture("vocab.txt", "a\nb\nc\nd\ne\nf\ng\nh");
+    let tokenizer = Tokenizer::new(&vocab_file).unwrap();
+    // Each target reverses its source.
+    let pairs = write_fixture("pairs.txt", "a b c\tc b a\nd e\te d\nf g h\th g f\nb d\td b\n");
+    let mut data_loader = PairDataLoader::new(&pairs, 2, 6, &tokenizer).unwrap();
+
+    let mut model: EncoderDecoderModel = EncoderDecoderModel::new(&config());
+    model.ignore_index = Some(tokenizer.pad_id);
+    let mut optimizer = AdamOptimizer::new(0.01);
+    model.train();
+    let mut losses = Vec::new();
+    for batch in data_loader.iter().take(40) {
+        model.zero_grad();
+        let (_, loss) = model.forward(&batch.source, &batch.input, Some(&batch.target), Some(&batch.source_mask)).unwrap();
+        let loss = loss.unwrap();
+        loss.backward();
+        optimizer.step(&mut model);
+        losses.push(loss.value().item());
+    }
+    assert!(losses[38] + losses[39] < 0.5 * (losses[0] + losses[1]), "loss did not drop: {:?}", losses);
+
+    let generated = model.generate("a b c", &tokenizer, &config()).unwrap();
+    assert!(generated.split_whitespace().count() <= 6);
+    assert!(model.position() <= 6);
+}
+
+#[test]
+fn test_encoder_decoder_checkpoint_round_trip() {
+    let path = write_fixture("model.bin", "");
+    let mut model: EncoderDecoderModel<f64> = EncoderDecoderModel::new(&config());
+    model.save_checkpoint(&path).unwrap();
+    let mut loaded: EncoderDecoderModel<f64> = EncoderDecoderModel::load_checkpoint(&path).unwrap();
+
+    let source = vec![vec![1, 2, 3]];
+    let input = vec![vec![8, 4]];
+    let (logits, _) = model.forward(&source, &input, None, None).unwrap();
+    let (loaded_logits, _) = loaded.forward(&source, &input, None, None).unwrap();
+    assert_eq!(*logits.value(), *loaded_logits.value());
+}
+
+#[test]
+fn test_encoder_decoder_fit_ignores_padding() {
+    let vocab_file = write_fixture("fit_vocab.txt", "a\nb\nc");
+    let tokenizer = Tokenizer::new(&vocab_file).unwrap();
+    let pairs = write_fixture("fit_pairs.txt", "a b c\tc\nb\ta b\n");
+    let mut data_loader = PairDataLoader::new(&pairs, 2, 6, &tokenizer).unwrap();
+
+    let mut model: EncoderDecoderModel = EncoderDecoderModel::new(&config());
+    assert_eq!(model.ignore_index, None);
+    let fit_config = Config {
+        num_epochs: 1,
+        checkpoint_interval: 100,
+        ..config()
+    };
+    model.fit(&mut data_loader, &fit_config).unwrap();
+    assert_eq!(model.ignore_index, Some(tokenizer.pad_id));
+
+    // Two pairs do not fill a batch of three.
+    let mut short_loader = PairDataLoader::new(&pairs, 3, 6, &tokenizer).unwrap();
+    assert!(matches!(model.fit(&mut short_loader, &fit_config), Err(Error::InvalidConfig(_))));
+}
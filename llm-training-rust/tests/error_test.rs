@@ -0,0 +1,100 @@
+use llm_training_rust::attention::Attention;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::config::Config;
+use llm_training_rust::data_loader::{DataLoader, PairDataLoader};
+use llm_training_rust::error::Error;
+use llm_training_rust::linear::Linear;
+use llm_training_rust::model::Model;
+use llm_training_rust::tensor::Tensor;
+use llm_training_rust::tokenizer::Tokenizer;
+use llm_training_rust::utils::load_model;
+
+fn scratch_file(name: &str, contents: &[u8]) -> String {
+    let dir = std::env::temp_dir().join("llm_training_rust_error_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_missing_files_are_io_errors() {
+    let missing = "does/not/exist.txt";
+
+    assert!(matches!(Config::from_file(missing), Err(Error::Io { path, .. }) if path == missing));
+    assert!(matches!(Tokenizer::new(missing), Err(Error::Io { .. })));
+
+    let vocab = scratch_file("vocab.txt", b"a\nb\n");
+    let tokenizer = Tokenizer::new(&vocab).unwrap();
+    assert!(matches!(DataLoader::new(missing, 2, 4, &tokenizer), Err(Error::Io { .. })));
+}
+
+#[test]
+fn test_bad_config_is_a_parse_error() {
+    let path = scratch_file("config.json", br#"{ "vocab_size": "lots" }"#);
+
+    let err = Config::from_file(&path).unwrap_err();
+    assert!(matches!(err, Error::Parse { .. }));
+    assert!(err.to_string().starts_with(&path), "{}", err);
+}
+
+#[test]
+fn test_corrupt_checkpoint_is_a_checkpoint_error() {
+    let path = scratch_file("model.bin", b"not a model");
+
+    assert!(matches!(load_model::<f32>(&path), Err(Error::Checkpoint { .. })));
+}
+
+#[test]
+fn test_shape_errors_name_the_layer() {
+    let mut linear: Linear = Linear::new(8, 4);
+    let err = linear.forward(&Var::constant(Tensor::ones(&[2, 3, 5]))).unwrap_err();
+    assert_eq!(err.to_string(), "Linear: expected input of shape [.., 8], got [2, 3, 5]");
+
+    let mut attention: Attention = Attention::new(&Config::default());
+    let err = attention.forward(&Var::constant(Tensor::ones(&[3, 32])), None).unwrap_err();
+    assert_eq!(err.to_string(), "Attention: expected input of shape [_, _, 32], got [3, 32]");
+}
+
+#[test]
+fn test_model_rejects_bad_batches() {
+    let config = Config::default();
+    let mut model: Model = Model::new(&config);
+
+    let ragged = vec![vec![1, 2, 3], vec![4, 5]];
+    assert!(matches!(model.forward(&ragged, None, None), Err(Error::ShapeMismatch { .. })));
+
+    let out_of_vocab = vec![vec![1, 2, 100]];
+    assert!(matches!(
+        model.forward(&out_of_vocab, None, None),
+        Err(Error::VocabMismatch { vocab_size: 100, token_id: 100 })
+    ));
+
+    let too_long = vec![vec![1; config.max_seq_len + 1]];
+    let err = model.forward(&too_long, None, None).unwrap_err();
+    assert!(matches!(&err, Error::ShapeMismatch { layer, .. } if layer == "PositionalEncoding"), "{}", err);
+}
+
+#[test]
+fn test_zero_batch_sizes_are_config_errors() {
+    let tokenizer = Tokenizer::new(&scratch_file("vocab.txt", b"a\nb\n")).unwrap();
+    let text = scratch_file("text.txt", b"a b a b a b a b a b");
+    let pairs = scratch_file("pairs.txt", b"a b\tb a\n");
+
+    assert!(matches!(DataLoader::new(&text, 0, 4, &tokenizer), Err(Error::InvalidConfig(_))));
+    assert!(matches!(DataLoader::new(&text, 2, 0, &tokenizer), Err(Error::InvalidConfig(_))));
+    assert!(matches!(PairDataLoader::new(&pairs, 0, 4, &tokenizer), Err(Error::InvalidConfig(_))));
+    assert!(matches!(PairDataLoader::new(&pairs, 1, 0, &tokenizer), Err(Error::InvalidConfig(_))));
+
+    // Ten tokens hold two windows of four, short of a batch of three.
+    let mut data_loader = DataLoader::new(&text, 3, 4, &tokenizer).unwrap();
+    assert!(data_loader.is_empty());
+    let config = Config {
+        vocab_size: tokenizer.vocab_size(),
+        max_seq_len: 4,
+        num_epochs: 1,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    assert!(matches!(model.fit(&mut data_loader, &config), Err(Error::InvalidConfig(_))));
+}
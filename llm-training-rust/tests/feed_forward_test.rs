@@ -1,5 +1,9 @@
-use llm_training_rust::feed_forward::FeedForward;
+use llm_training_rust::activation::Activation;
+use llm_training_rust::feed_forward::{FeedForward, FfnType};
 use llm_training_rust::config::Config;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::module::Module;
+use llm_training_rust::tensor::Tensor;
 
 #[test]
 fn test_feed_forward_forward() {
@@ -15,15 +19,15 @@ fn test_feed_forward_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut feed_forward = FeedForward::new(&config);
+    let mut feed_forward: FeedForward = FeedForward::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = feed_forward.forward(&input);
+    let input = Var::constant(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = feed_forward.forward(&input).unwrap();
 
-    assert_eq!(output.len(), config.max_seq_len);
-    assert_eq!(output[0].len(), config.embedding_dim);
+    assert_eq!(output.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
 }
 
 #[test]
@@ -40,16 +44,103 @@ fn test_feed_forward_backward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut feed_forward = FeedForward::new(&config);
+    let mut feed_forward: FeedForward = FeedForward::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = feed_forward.forward(&input);
+    let input = Var::leaf(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = feed_forward.forward(&input).unwrap();
 
-    let grad_output = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let grad_input = feed_forward.backward(&grad_output);
+    let grad_output = Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]);
+    output.backward_with(grad_output);
+    let grad_input = input.grad().unwrap();
 
-    assert_eq!(grad_input.len(), config.max_seq_len);
-    assert_eq!(grad_input[0].len(), config.embedding_dim);
-}
\ No newline at end of file
+    assert_eq!(grad_input.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
+}
+
+#[test]
+fn test_gated_feed_forward() {
+    for ffn_type in [FfnType::SwiGlu, FfnType::GeGlu] {
+        let config = Config {
+            embedding_dim: 32,
+            feed_forward_dim: 96,
+            ffn_type,
+            ..Default::default()
+        };
+        let mut feed_forward: FeedForward = FeedForward::new(&config);
+        assert_eq!(feed_forward.ffn_type(), ffn_type);
+        assert_eq!(feed_forward.hidden_dim(), 64);
+
+        let shapes = feed_forward
+            .named_parameters()
+            .into_iter()
+            .filter(|(name, _)| name.ends_with("weight"))
+            .map(|(name, param)| (name, param.shape().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shapes,
+            [
+                ("linear1.weight".to_string(), vec![32, 64]),
+                ("gate.weight".to_string(), vec![32, 64]),
+                ("linear2.weight".to_string(), vec![64, 32]),
+            ]
+        );
+
+        let input = Var::constant(Tensor::ones(&[2, 5, 32]));
+        assert_eq!(feed_forward.forward(&input).unwrap().shape(), [2, 5, 32]);
+    }
+}
+
+#[test]
+fn test_gated_hidden_dim_keeps_parameter_count() {
+    assert_eq!(FfnType::Mlp.hidden_dim(96), 96);
+    assert_eq!(FfnType::SwiGlu.hidden_dim(96), 64);
+    assert_eq!(FfnType::GeGlu.hidden_dim(100), 66);
+    assert_eq!(FfnType::SwiGlu.hidden_dim(1), 1);
+
+    let weights = |ffn_type| {
+        let config = Config { embedding_dim: 16, feed_forward_dim: 48, ffn_type, ..Default::default() };
+        let feed_forward: FeedForward = FeedForward::new(&config);
+        feed_forward
+            .named_parameters()
+            .into_iter()
+            .filter(|(name, _)| name.ends_with("weight"))
+            .map(|(_, param)| param.shape().iter().product::<usize>())
+            .sum::<usize>()
+    };
+    assert_eq!(weights(FfnType::SwiGlu), weights(FfnType::Mlp));
+}
+
+#[test]
+fn test_feed_forward_activation() {
+    let default: FeedForward = FeedForward::new(&Config::default());
+    assert_eq!(default.activation(), Activation::GeluTanh);
+    let swiglu: FeedForward = FeedForward::new(&Config { ffn_type: FfnType::SwiGlu, ..Default::default() });
+    assert_eq!(swiglu.activation(), Activation::Silu);
+
+    // An explicit activation overrides the one the block type is named for.
+    let config = Config {
+        embedding_dim: 4,
+        feed_forward_dim: 8,
+        dropout_rate: 0.0,
+        ffn_type: FfnType::GeGlu,
+        activation: Some(Activation::ReluSquared),
+        ..Default::default()
+    };
+    let mut feed_forward: FeedForward<f64> = FeedForward::new(&config);
+    assert_eq!(feed_forward.activation(), Activation::ReluSquared);
+
+    // A gate that is negative everywhere zeroes the hidden layer, leaving only linear2's bias.
+    for (name, param) in feed_forward.named_parameters_mut() {
+        if name.starts_with("gate") {
+            let negative = param.value().map(|_| -1.0);
+            *param.value_mut() = negative;
+        }
+    }
+    let input = Var::constant(Tensor::zeros(&[1, 4]));
+    let output = feed_forward.forward(&input).unwrap().value().to_vec();
+    let params = feed_forward.named_parameters();
+    let (_, bias) = params.iter().find(|(name, _)| name == "linear2.bias").unwrap();
+    assert_eq!(output, bias.value().to_vec());
+}
@@ -0,0 +1,86 @@
+use llm_training_rust::config::Config;
+use llm_training_rust::float::{Bf16, DType, Float};
+use llm_training_rust::model::Model;
+use llm_training_rust::module::Module;
+use llm_training_rust::optimizer::AdamOptimizer;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_bf16_rounds_to_nearest_even() {
+    assert_eq!(Bf16::from_f32(1.0).to_bits(), 0x3f80);
+    assert_eq!(Bf16::from_f32(-2.0).to_f32(), -2.0);
+
+    // 1 + 2^-8 is exactly halfway between 1 and the next bf16; ties go to the even mantissa.
+    assert_eq!(Bf16::from_f32(1.0 + 2f32.powi(-8)).to_f32(), 1.0);
+    assert_eq!(Bf16::from_f32(1.0 + 3.0 * 2f32.powi(-8)).to_f32(), 1.0 + 2f32.powi(-6));
+
+    assert!(Bf16::from_f32(f32::NAN).to_f32().is_nan());
+    assert_eq!(Bf16::from_f32(f32::INFINITY).to_f32(), f32::INFINITY);
+    assert_eq!(Bf16::from_f32(f32::MAX).to_f32(), f32::INFINITY);
+}
+
+#[test]
+fn test_bf16_arithmetic() {
+    let a = Bf16::from_f64(1.5);
+    let b = Bf16::from_f64(0.25);
+    assert_eq!((a + b).to_f64(), 1.75);
+    assert_eq!((a * b).to_f64(), 0.375);
+    assert_eq!((-a).to_f64(), -1.5);
+    assert!(b < a);
+    assert_eq!(a.max(b), a);
+}
+
+#[test]
+fn test_bf16_matmul_close_to_f32() {
+    let a = Tensor::<f32>::new((0..12).map(|x| x as f32 * 0.1).collect(), &[3, 4]);
+    let b = Tensor::<f32>::new((0..8).map(|x| 1.0 - x as f32 * 0.2).collect(), &[4, 2]);
+
+    let exact = a.matmul(&b);
+    let low = a.cast::<Bf16>().matmul(&b.cast::<Bf16>()).cast::<f32>();
+
+    for (x, y) in exact.to_vec().iter().zip(low.to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 0.02 * x.abs().max(1.0));
+    }
+}
+
+#[test]
+fn test_config_dtype() {
+    assert_eq!(Config::default().dtype, DType::F32);
+
+    let json = r#"{
+        "vocab_size": 100, "max_seq_len": 20, "embedding_dim": 32, "num_layers": 2,
+        "num_heads": 4, "feed_forward_dim": 64, "dropout_rate": 0.1, "learning_rate": 0.001,
+        "batch_size": 2, "num_epochs": 1, "checkpoint_interval": 10, "dtype": "bf16"
+    }"#;
+    let config: Config = serde_json::from_str(json).unwrap();
+    assert_eq!(config.dtype, DType::Bf16);
+}
+
+fn train_step<T: Float>() -> (f64, f64) {
+    let config = Config::default();
+    let mut model: Model<T> = Model::new(&config);
+    let mut optimizer = AdamOptimizer::new(0.01);
+    model.eval();
+
+    let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+    let target = vec![vec![2, 3, 4, 5], vec![6, 7, 8, 9]];
+
+    let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+    let loss = loss.unwrap();
+    loss.backward();
+    optimizer.step(&mut model);
+    let before = loss.value().item().to_f64();
+
+    model.zero_grad();
+    let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+    (before, loss.unwrap().value().item().to_f64())
+}
+
+#[test]
+fn test_model_trains_in_every_dtype() {
+    for (before, after) in [train_step::<f32>(), train_step::<f64>(), train_step::<Bf16>()] {
+        assert!(before.is_finite() && after.is_finite());
+        assert!(after < before, "loss did not decrease: {} -> {}", before, after);
+    }
+}
@@ -3,19 +3,35 @@ use approx::assert_abs_diff_eq;
 
 #[test]
 fn test_gelu_forward() {
-    let input = vec![1.0, 2.0, 3.0];
+    let input = [1.0, 2.0, 3.0];
     let output = input.iter().map(|&x| gelu(x)).collect::<Vec<_>>();
 
     assert_eq!(output.len(), input.len());
+    assert_abs_diff_eq!(gelu(0.0), 0.0);
+    assert!(output.windows(2).all(|w| w[0] < w[1]));
 }
 
 #[test]
 fn test_gelu_backward() {
-    let input = vec![1.0, 2.0, 3.0];
-    let output = input.iter().map(|&x| gelu(x)).collect::<Vec<_>>();
+    let input = [1.0, 2.0, 3.0];
 
-    let grad_output = vec![1.0, 1.0, 1.0];
-    let grad_input = output.iter().zip(input.iter()).map(|(&o, &i)| gelu_backward(o, i)).collect::<Vec<_>>();
+    let grad_output = [1.0, 1.0, 1.0];
+    let grad_input = grad_output.iter().zip(input.iter()).map(|(&g, &i)| gelu_backward(g, i)).collect::<Vec<_>>();
 
     assert_eq!(grad_input.len(), input.len());
+
+    // Matches a central difference of the forward pass.
+    let eps = 1e-6;
+    for &x in &[-3.0, -1.0, -0.1, 0.0, 0.5, 1.0, 2.0, 3.0] {
+        let numerical = (gelu(x + eps) - gelu(x - eps)) / (2.0 * eps);
+        assert_abs_diff_eq!(gelu_backward(1.0, x), numerical, epsilon = 1e-8);
+    }
+}
+
+#[test]
+fn test_gelu_known_values() {
+    // GELU(x) = x * Phi(x); the tanh approximation agrees to about 1e-3.
+    assert_abs_diff_eq!(gelu(1.0), 0.8413, epsilon = 1e-3);
+    assert_abs_diff_eq!(gelu(-1.0), -0.1587, epsilon = 1e-3);
+    assert_abs_diff_eq!(gelu(3.0), 2.9960, epsilon = 1e-3);
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use llm_training_rust::attention::Streaming;
+use llm_training_rust::config::Config;
+use llm_training_rust::error::Error;
+use llm_training_rust::generation::{generate, sample_multinomial};
+use llm_training_rust::model::Model;
+use llm_training_rust::module::Module;
+use llm_training_rust::positional_encoding::PositionEncoding;
+use llm_training_rust::rng;
+use llm_training_rust::tokenizer::Tokenizer;
+
+fn tokenizer() -> Tokenizer {
+    let dir = std::env::temp_dir().join("llm_training_rust_generation_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("vocab.txt");
+    std::fs::write(&path, "to\nbe\nor\nnot\nthat\nis\nthe\nquestion").unwrap();
+    Tokenizer::new(path.to_str().unwrap()).unwrap()
+}
+
+fn config(max_seq_len: usize) -> Config {
+    Config {
+        vocab_size: 10,
+        max_seq_len,
+        embedding_dim: 16,
+        num_layers: 2,
+        feed_forward_dim: 32,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_generate_matches_full_forward_sampling() {
+    let tokenizer = tokenizer();
+    let mut model: Model<f64> = Model::new(&config(20));
+
+    rng::manual_seed(5);
+    let text = generate(&mut model, "to be", &tokenizer, 10).unwrap();
+
+    // The same sampling loop, re-running the whole context every step.
+    rng::manual_seed(5);
+    let mut input_ids = tokenizer.encode("to be");
+    let mut generated_ids = Vec::new();
+    for _ in 0..10 {
+        let (logits, _) = model.forward(&[input_ids.clone()], None, None).unwrap();
+        let logits = logits.value();
+        let last = logits.narrow(1, input_ids.len() - 1, 1).reshape(&[10]).softmax(0);
+        let next_id = sample_multinomial(&last.to_vec());
+        if next_id == tokenizer.eos_id {
+            break;
+        }
+        generated_ids.push(next_id);
+        input_ids.push(next_id);
+    }
+
+    assert_eq!(text, tokenizer.decode(&generated_ids));
+}
+
+#[test]
+fn test_generate_from_empty_prompt_starts_at_eos() {
+    let tokenizer = tokenizer();
+    let mut model: Model<f64> = Model::new(&config(20));
+
+    rng::manual_seed(3);
+    let text = generate(&mut model, "", &tokenizer, 5).unwrap();
+    rng::manual_seed(3);
+    let from_eos = generate(&mut model, "<eos>", &tokenizer, 5).unwrap();
+    assert_eq!(text, from_eos);
+    assert!(text.split_whitespace().count() <= 5);
+}
+
+#[test]
+fn test_generate_runs_past_max_seq_len() {
+    let tokenizer = tokenizer();
+    let mut model: Model = Model::new(&config(4));
+    model.eval();
+
+    let text = generate(&mut model, "to be or not that is", &tokenizer, 20).unwrap();
+    assert!(text.split_whitespace().count() <= 20);
+    assert!(model.position() <= 4);
+}
+
+#[test]
+fn test_streaming_generation_runs_in_constant_memory() {
+    let tokenizer = tokenizer();
+    let config = Config {
+        position_encoding: PositionEncoding::Rope,
+        streaming: Some(Streaming { num_sinks: 1, window: 4 }),
+        max_new_tokens: Some(60),
+        ..config(6)
+    };
+    assert!(config.validate().is_ok());
+    let mut model: Model = Model::new(&config);
+
+    let text = model.generate("to be or", &tokenizer, &config).unwrap();
+    assert!(text.split_whitespace().count() <= 60);
+    assert!(model.position() <= 4);
+
+    // Without streaming, the seventh token would run past `max_seq_len`.
+    model.clear_cache();
+    for step in 0..40 {
+        model.forward_step(&[vec![step % 8]]).unwrap();
+        assert!(model.position() <= 4, "cache grew to {} positions", model.position());
+    }
+}
+
+#[test]
+fn test_streaming_rejects_positions_added_to_embeddings() {
+    let streaming = Some(Streaming { num_sinks: 1, window: 4 });
+    for position_encoding in [PositionEncoding::Sinusoidal, PositionEncoding::Learned] {
+        let mut model: Model = Model::new(&Config { position_encoding, ..config(6) });
+        assert!(matches!(model.set_streaming(streaming), Err(Error::InvalidConfig(_))));
+    }
+
+    let mut model: Model = Model::new(&Config { position_encoding: PositionEncoding::Alibi, ..config(6) });
+    assert!(model.set_streaming(streaming).is_ok());
+    let too_wide = Some(Streaming { num_sinks: 2, window: 5 });
+    assert!(matches!(model.set_streaming(too_wide), Err(Error::InvalidConfig(_))));
+}
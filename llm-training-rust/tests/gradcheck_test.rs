@@ -0,0 +1,179 @@
+use llm_training_rust::attention::{padding_mask, Attention};
+use llm_training_rust::autograd::Var;
+use llm_training_rust::config::Config;
+use llm_training_rust::embedding::Embedding;
+use llm_training_rust::activation::Activation;
+use llm_training_rust::feed_forward::{FeedForward, FfnType};
+use llm_training_rust::gradcheck::gradcheck;
+use llm_training_rust::init::Init;
+use llm_training_rust::layer_norm::LayerNorm;
+use llm_training_rust::linear::Linear;
+use llm_training_rust::model::Model;
+use llm_training_rust::positional_encoding::PositionEncoding;
+use llm_training_rust::transformer::TransformerLayer;
+
+fn config() -> Config {
+    Config {
+        vocab_size: 7,
+        max_seq_len: 4,
+        embedding_dim: 4,
+        num_layers: 1,
+        num_heads: 2,
+        feed_forward_dim: 8,
+        ..Default::default()
+    }
+}
+
+fn random_input(shape: &[usize]) -> Var<f64> {
+    Var::leaf(Init::Normal(1.0).tensor(shape, 1, 1))
+}
+
+#[test]
+fn test_gradcheck_linear() {
+    let mut linear: Linear<f64> = Linear::new(4, 3);
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut linear, &[&input], |m| m.forward(&input).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_attention() {
+    let mut attention: Attention<f64> = Attention::new(&config());
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut attention, &[&input], |m| m.forward(&input, None).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_rotary_attention() {
+    let config = Config {
+        position_encoding: PositionEncoding::Rope,
+        ..config()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut attention, &[&input], |m| m.forward(&input, None).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_multi_query_attention() {
+    let config = Config {
+        num_kv_heads: Some(1),
+        ..config()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut attention, &[&input], |m| m.forward(&input, None).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_feed_forward() {
+    let mut feed_forward: FeedForward<f64> = FeedForward::new(&config());
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut feed_forward, &[&input], |m| m.forward(&input).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_gated_feed_forward() {
+    for ffn_type in [FfnType::SwiGlu, FfnType::GeGlu] {
+        let mut feed_forward: FeedForward<f64> = FeedForward::new(&Config { ffn_type, ..config() });
+        let input = random_input(&[2, 3, 4]);
+
+        gradcheck(&mut feed_forward, &[&input], |m| m.forward(&input).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn test_gradcheck_feed_forward_activations() {
+    // ReLU is left out: a finite difference straddling its kink disagrees with any derivative.
+    for activation in [Activation::Gelu, Activation::Silu, Activation::ReluSquared] {
+        let mut feed_forward: FeedForward<f64> = FeedForward::new(&Config { activation: Some(activation), ..config() });
+        let input = random_input(&[2, 3, 4]);
+
+        gradcheck(&mut feed_forward, &[&input], |m| m.forward(&input).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn test_gradcheck_layer_norm() {
+    let mut layer_norm: LayerNorm<f64> = LayerNorm::new(4);
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut layer_norm, &[&input], |m| m.forward(&input).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_embedding() {
+    let mut embedding: Embedding<f64> = Embedding::new(7, 4);
+
+    // Repeated ids check that gradients for the same row accumulate.
+    gradcheck(&mut embedding, &[], |m| m.forward(&[1, 3, 1, 0]).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_transformer_layer() {
+    let mut layer: TransformerLayer<f64> = TransformerLayer::new(&config());
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut layer, &[&input], |m| m.forward(&input, None).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_model() {
+    let mut model: Model<f64> = Model::new(&config());
+    let input = vec![vec![1, 2, 3], vec![4, 5, 6]];
+    let target = vec![vec![2, 3, 4], vec![5, 6, 0]];
+
+    gradcheck(&mut model, &[], |m| m.forward(&input, Some(&target), None).unwrap().1.unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_catches_wrong_derivative() {
+    let mut linear: Linear<f64> = Linear::new(4, 3);
+    let input = random_input(&[2, 4]);
+
+    // d/dx x^2 is 2x, not x.
+    let result = gradcheck(&mut linear, &[&input], |m| m.forward(&input).unwrap().map(|x| x * x, |x| x));
+
+    let err = result.unwrap_err();
+    assert_eq!(err.name, "input.0");
+    assert!((err.analytical - err.numerical).abs() > 1e-3, "{}", err);
+}
+
+#[test]
+fn test_gradcheck_alibi_attention() {
+    let config = Config {
+        position_encoding: PositionEncoding::Alibi,
+        ..config()
+    };
+    let mut attention: Attention<f64> = Attention::new(&config);
+    let input = random_input(&[2, 3, 4]);
+
+    gradcheck(&mut attention, &[&input], |m| m.forward(&input, None).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_cross_attention() {
+    let mut attention: Attention<f64> = Attention::cross(&config());
+    let input = random_input(&[2, 3, 4]);
+    let memory = random_input(&[2, 2, 4]);
+    let mask = padding_mask(&[vec![true, true], vec![true, false]]);
+
+    gradcheck(&mut attention, &[&input, &memory], |m| m.forward_cross(&input, &memory, Some(&mask)).unwrap()).unwrap();
+}
+
+#[test]
+fn test_gradcheck_model_with_learned_positions() {
+    let config = Config {
+        position_encoding: PositionEncoding::Learned,
+        ..config()
+    };
+    let mut model: Model<f64> = Model::new(&config);
+    let input = vec![vec![1, 2, 3], vec![4, 5, 6]];
+    let target = vec![vec![2, 3, 4], vec![5, 6, 0]];
+
+    gradcheck(&mut model, &[], |m| m.forward(&input, Some(&target), None).unwrap().1.unwrap()).unwrap();
+}
@@ -1,29 +1,29 @@
 use llm_training_rust::layer_norm::LayerNorm;
-use approx::assert_abs_diff_eq;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::tensor::Tensor;
 
 #[test]
 fn test_layer_norm_forward() {
     let dim = 32;
-    let mut layer_norm = LayerNorm::new(dim);
+    let layer_norm: LayerNorm = LayerNorm::new(dim);
 
-    let input = vec![vec![1.0; dim]; 3];
-    let output = layer_norm.forward(&input);
+    let input = Var::constant(Tensor::ones(&[3, dim]));
+    let output = layer_norm.forward(&input).unwrap();
 
-    assert_eq!(output.len(), input.len());
-    assert_eq!(output[0].len(), dim);
+    assert_eq!(output.shape(), input.shape());
 }
 
 #[test]
 fn test_layer_norm_backward() {
     let dim = 32;
-    let mut layer_norm = LayerNorm::new(dim);
+    let layer_norm: LayerNorm = LayerNorm::new(dim);
 
-    let input = vec![vec![1.0; dim]; 3];
-    let output = layer_norm.forward(&input);
+    let input = Var::leaf(Tensor::ones(&[3, dim]));
+    let output = layer_norm.forward(&input).unwrap();
 
-    let grad_output = vec![vec![1.0; dim]; 3];
-    let grad_input = layer_norm.backward(&grad_output, &input);
+    let grad_output = Tensor::ones(&[3, dim]);
+    output.backward_with(grad_output);
+    let grad_input = input.grad().unwrap();
 
-    assert_eq!(grad_input.len(), input.len());
-    assert_eq!(grad_input[0].len(), dim);
+    assert_eq!(grad_input.shape(), input.shape().as_slice());
 }
\ No newline at end of file
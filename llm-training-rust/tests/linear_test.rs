@@ -0,0 +1,42 @@
+use llm_training_rust::autograd::Var;
+use llm_training_rust::init::Init;
+use llm_training_rust::linear::Linear;
+use llm_training_rust::module::Module;
+use llm_training_rust::tensor::Tensor;
+
+#[test]
+fn test_linear_forward() {
+    let mut linear: Linear = Linear::new(8, 4);
+
+    let input = Var::constant(Tensor::ones(&[2, 3, 8]));
+    let output = linear.forward(&input).unwrap();
+
+    assert_eq!(output.shape(), [2, 3, 4]);
+    assert_eq!(linear.parameters().len(), 2);
+}
+
+#[test]
+fn test_linear_init_and_bias() {
+    let mut linear: Linear = Linear::with_init(3, 2, false, Init::Constant(0.5));
+    assert_eq!(linear.named_parameters().len(), 1);
+
+    let input = Var::constant(Tensor::new(vec![1.0, 2.0, 3.0], &[1, 3]));
+    let output = linear.forward(&input).unwrap();
+
+    assert_eq!(output.value().to_vec(), vec![3.0, 3.0]);
+}
+
+#[test]
+fn test_linear_backward() {
+    let mut linear: Linear = Linear::with_init(3, 2, true, Init::Constant(1.0));
+
+    let input = Var::leaf(Tensor::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]));
+    linear.forward(&input).unwrap().sum().backward();
+
+    let params = linear.named_parameters();
+    assert_eq!(params[0].0, "weight");
+    assert_eq!(params[0].1.grad().unwrap().to_vec(), vec![5.0, 5.0, 7.0, 7.0, 9.0, 9.0]);
+    assert_eq!(params[1].0, "bias");
+    assert_eq!(params[1].1.grad().unwrap().to_vec(), vec![2.0, 2.0]);
+    assert_eq!(input.grad().unwrap().to_vec(), vec![2.0; 6]);
+}
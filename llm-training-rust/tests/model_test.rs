@@ -1,5 +1,16 @@
 use llm_training_rust::model::Model;
+use llm_training_rust::data_loader::{pad_batch, DataLoader};
+use approx::assert_abs_diff_eq;
+use llm_training_rust::activation::Activation;
 use llm_training_rust::config::Config;
+use llm_training_rust::error::Error;
+use llm_training_rust::feed_forward::FfnType;
+use llm_training_rust::module::Module;
+use llm_training_rust::optimizer::AdamOptimizer;
+use llm_training_rust::positional_encoding::{PositionEncoding, PositionScaling};
+use llm_training_rust::rng;
+use llm_training_rust::tensor::Tensor;
+use llm_training_rust::tokenizer::Tokenizer;
 
 #[test]
 fn test_model_forward() {
@@ -15,18 +26,17 @@ fn test_model_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut model = Model::new(&config);
+    let mut model: Model = Model::new(&config);
 
     let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
     let target = vec![vec![2, 3, 4, 5], vec![6, 7, 8, 9]];
 
-    let (logits, loss) = model.forward(&input, Some(&target));
+    let (logits, loss) = model.forward(&input, Some(&target), None).unwrap();
 
-    assert_eq!(logits.len(), 2);
-    assert_eq!(logits[0].len(), 4);
-    assert_eq!(logits[0][0].len(), config.vocab_size);
+    assert_eq!(logits.shape(), [2, 4, config.vocab_size]);
 
     assert!(loss.is_some());
 }
@@ -45,16 +55,384 @@ fn test_model_backward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut model = Model::new(&config);
+    let mut model: Model = Model::new(&config);
 
     let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
     let target = vec![vec![2, 3, 4, 5], vec![6, 7, 8, 9]];
 
-    let (logits, _) = model.forward(&input, Some(&target));
-    model.backward(&logits, &input, &target);
+    let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+    loss.unwrap().backward();
 
-    // Check if the gradients are computed for all parameters
-    // You can add more specific assertions based on your implementation
-}
\ No newline at end of file
+    for (name, param) in model.named_parameters() {
+        let grad = param.grad().unwrap_or_else(|| panic!("no gradient for {}", name));
+        assert_eq!(grad.shape(), param.shape().as_slice(), "gradient shape for {}", name);
+    }
+
+    model.zero_grad();
+    assert!(model.parameters().iter().all(|p| p.grad().is_none()));
+}
+
+#[test]
+fn test_model_named_parameters() {
+    let config = Config {
+        vocab_size: 100,
+        max_seq_len: 20,
+        embedding_dim: 32,
+        num_layers: 2,
+        num_heads: 4,
+        feed_forward_dim: 64,
+        dropout_rate: 0.1,
+        learning_rate: 0.001,
+        batch_size: 2,
+        num_epochs: 1,
+        checkpoint_interval: 10,
+        ..Default::default()
+    };
+
+    let model: Model = Model::new(&config);
+    let names = model.named_parameters().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+
+    assert!(names.contains(&"embedding.embedding_matrix".to_string()));
+    assert!(names.contains(&"transformer.layers.1.attention.query_matrix.weight".to_string()));
+    assert!(names.contains(&"transformer.layers.0.layer_norm2.gamma".to_string()));
+    assert!(names.contains(&"linear.weight".to_string()));
+    assert_eq!(model.parameters().len(), names.len());
+}
+
+#[test]
+fn test_model_eval_is_deterministic() {
+    let config = Config {
+        vocab_size: 100,
+        max_seq_len: 20,
+        embedding_dim: 32,
+        num_layers: 2,
+        num_heads: 4,
+        feed_forward_dim: 64,
+        dropout_rate: 0.5,
+        learning_rate: 0.001,
+        batch_size: 2,
+        num_epochs: 1,
+        checkpoint_interval: 10,
+        ..Default::default()
+    };
+
+    let mut model: Model = Model::new(&config);
+    model.eval();
+
+    let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+    let (first, _) = model.forward(&input, None, None).unwrap();
+    let (second, _) = model.forward(&input, None, None).unwrap();
+
+    assert_eq!(*first.value(), *second.value());
+}
+
+#[test]
+fn test_perplexity_restores_training_mode() {
+    let config = Config {
+        dropout_rate: 0.5,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    let input = vec![vec![1, 2, 3, 4]];
+    let target = vec![vec![2, 3, 4, 5]];
+    assert!(model.is_training());
+
+    // Dropout is off inside `perplexity`, so repeated calls agree.
+    let first = model.perplexity(&input, &target).unwrap();
+    assert_eq!(model.perplexity(&input, &target).unwrap(), first);
+    assert!(model.is_training());
+
+    model.eval();
+    model.perplexity(&input, &target).unwrap();
+    assert!(!model.is_training());
+}
+
+#[test]
+fn test_perplexity_restores_training_mode_without_layers() {
+    let config = Config {
+        num_layers: 0,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    model.train();
+    model.perplexity(&[vec![1, 2, 3]], &[vec![2, 3, 4]]).unwrap();
+    assert!(model.is_training());
+    model.eval();
+    assert!(!model.is_training());
+}
+
+#[test]
+fn test_model_logits_do_not_see_future_tokens() {
+    let config = Config {
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+
+    let (a, _) = model.forward(&[vec![1, 2, 3, 4, 5, 6]], None, None).unwrap();
+    let (b, _) = model.forward(&[vec![1, 2, 3, 9, 8, 7]], None, None).unwrap();
+
+    assert_eq!(a.value().narrow(1, 0, 3), b.value().narrow(1, 0, 3));
+    assert_ne!(a.value().narrow(1, 3, 1), b.value().narrow(1, 3, 1));
+}
+
+#[test]
+fn test_model_padding_does_not_change_real_positions() {
+    let config = Config {
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    assert_eq!(model.ignore_index, None);
+    let pad_id = 42;
+    model.ignore_index = Some(pad_id);
+
+    let (input, attention_mask) = pad_batch(&[vec![1, 2, 3], vec![4, 5, 6, 7, 8]], pad_id);
+    let (target, _) = pad_batch(&[vec![2, 3, 4], vec![5, 6, 7, 8, 9]], pad_id);
+    let (padded, padded_loss) = model.forward(&input, Some(&target), Some(&attention_mask)).unwrap();
+
+    let (short, short_loss) = model.forward(&[vec![1, 2, 3]], Some(&[vec![2, 3, 4]]), None).unwrap();
+    let (long, long_loss) = model.forward(&[vec![4, 5, 6, 7, 8]], Some(&[vec![5, 6, 7, 8, 9]]), None).unwrap();
+
+    for (x, y) in padded.value().narrow(0, 0, 1).narrow(1, 0, 3).to_vec().iter().zip(short.value().to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 1e-5);
+    }
+    for (x, y) in padded.value().narrow(0, 1, 1).to_vec().iter().zip(long.value().to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 1e-5);
+    }
+
+    // The padded loss is the mean over the 8 real targets.
+    let expected = (3.0 * short_loss.unwrap().value().item() + 5.0 * long_loss.unwrap().value().item()) / 8.0;
+    assert_abs_diff_eq!(padded_loss.unwrap().value().item(), expected, epsilon = 1e-5);
+}
+
+#[test]
+fn test_model_ignored_targets_do_not_contribute_to_loss() {
+    let config = Config {
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    let pad_id = 42;
+    model.ignore_index = Some(pad_id);
+
+    let (_, loss) = model.forward(&[vec![1, 2, 3, 4]], Some(&[vec![2, 3, pad_id, pad_id]]), None).unwrap();
+    let loss = loss.unwrap();
+    loss.backward();
+    let grads = model.parameters().iter().map(|p| p.grad().unwrap().to_vec()).collect::<Vec<_>>();
+
+    // Causal attention means positions 0 and 1 see the same context in the truncated sequence.
+    model.zero_grad();
+    let (_, truncated) = model.forward(&[vec![1, 2]], Some(&[vec![2, 3]]), None).unwrap();
+    let truncated = truncated.unwrap();
+    truncated.backward();
+
+    assert_abs_diff_eq!(loss.value().item(), truncated.value().item(), epsilon = 1e-5);
+    for (param, expected) in model.parameters().iter().zip(&grads) {
+        for (x, y) in param.grad().unwrap().to_vec().iter().zip(expected) {
+            assert_abs_diff_eq!(*x, *y, epsilon = 1e-5);
+        }
+    }
+}
+
+#[test]
+fn test_model_forward_step_matches_forward() {
+    let config = Config {
+        max_seq_len: 6,
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let mut model: Model<f64> = Model::new(&config);
+    let input = vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 10, 11, 12]];
+    let (full, _) = model.forward(&input, None, None).unwrap();
+
+    let prompt = input.iter().map(|s| s[..3].to_vec()).collect::<Vec<_>>();
+    let mut steps = vec![model.forward_step(&prompt).unwrap().value().clone()];
+    for i in 3..6 {
+        let next = input.iter().map(|s| vec![s[i]]).collect::<Vec<_>>();
+        steps.push(model.forward_step(&next).unwrap().value().clone());
+    }
+    assert_eq!(model.position(), 6);
+
+    let incremental = Tensor::cat(&steps.iter().collect::<Vec<_>>(), 1);
+    for (a, b) in full.value().to_vec().iter().zip(incremental.to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-10);
+    }
+
+    // The caches are full at `max_seq_len`.
+    assert!(model.forward_step(&[vec![1], vec![2]]).is_err());
+    model.clear_cache();
+    assert!(model.forward_step(&[vec![1], vec![2]]).is_ok());
+}
+
+#[test]
+fn test_from_checkpoint_extends_context() {
+    let dir = std::env::temp_dir().join("llm_training_rust_model_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("short_context.bin").to_str().unwrap().to_string();
+
+    let short = Config {
+        max_seq_len: 8,
+        position_encoding: PositionEncoding::Rope,
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let model: Model<f64> = Model::new(&short);
+    model.save_checkpoint(&path).unwrap();
+
+    let long = Config {
+        max_seq_len: 32,
+        position_scaling: Some(PositionScaling::Linear { factor: 4.0 }),
+        ..short.clone()
+    };
+    assert!(long.validate().is_ok());
+    let mut model: Model<f64> = Model::from_checkpoint(&path, &long).unwrap();
+    assert_eq!(model.max_seq_len(), 32);
+    assert_eq!(model.position_scaling(), long.position_scaling);
+    let rotary = model.transformer().layers()[0].attention().rotary().unwrap();
+    assert_eq!(rotary.scaling(), long.position_scaling);
+
+    // A short fine-tune at the new length.
+    let input = (0..2).map(|b| (0..32).map(|i| (3 * i + b) % 50).collect::<Vec<_>>()).collect::<Vec<_>>();
+    let target = input.iter().map(|row| row.iter().map(|&id| (id + 1) % 50).collect()).collect::<Vec<Vec<_>>>();
+    let mut optimizer = AdamOptimizer::new(0.01);
+    let mut losses = Vec::new();
+    for _ in 0..5 {
+        model.zero_grad();
+        let loss = model.forward(&input, Some(&target), None).unwrap().1.unwrap();
+        loss.backward();
+        optimizer.step(&mut model);
+        losses.push(loss.value().item());
+    }
+    assert!(losses[4] < losses[0], "{:?}", losses);
+
+    // The scaling is saved with the fine-tuned model.
+    model.save_checkpoint(&path).unwrap();
+    let reloaded: Model<f64> = Model::load_checkpoint(&path).unwrap();
+    assert_eq!(reloaded.position_scaling(), long.position_scaling);
+
+    let sinusoidal = Config { position_encoding: PositionEncoding::Sinusoidal, ..long.clone() };
+    assert!(matches!(Model::<f64>::from_checkpoint(&path, &sinusoidal), Err(Error::Checkpoint { .. })));
+}
+
+#[test]
+fn test_from_checkpoint_rejects_mismatched_config() {
+    let dir = std::env::temp_dir().join("llm_training_rust_model_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("mismatched.bin").to_str().unwrap().to_string();
+    let config = Config {
+        position_encoding: PositionEncoding::Rope,
+        ..Default::default()
+    };
+    let model: Model<f64> = Model::new(&config);
+    model.save_checkpoint(&path).unwrap();
+    assert!(Model::<f64>::from_checkpoint(&path, &config).is_ok());
+
+    let checkpoint_error = |config: Config, needle: &str| match Model::<f64>::from_checkpoint(&path, &config) {
+        Err(Error::Checkpoint { message, .. }) => assert!(message.contains(needle), "{}", message),
+        other => panic!("expected a checkpoint error mentioning {:?}, got {:?}", needle, other.err()),
+    };
+    checkpoint_error(Config { vocab_size: config.vocab_size + 1, ..config.clone() }, "vocab_size");
+    checkpoint_error(Config { embedding_dim: 64, ..config.clone() }, "embedding_dim");
+    checkpoint_error(Config { num_heads: 2, ..config.clone() }, "num_heads");
+    checkpoint_error(Config { num_kv_heads: Some(2), ..config.clone() }, "num_kv_heads");
+    checkpoint_error(Config { num_layers: config.num_layers + 1, ..config.clone() }, "num_layers");
+    checkpoint_error(Config { feed_forward_dim: config.feed_forward_dim * 2, ..config.clone() }, "feed_forward_dim");
+    // A gated block two thirds as wide, so only its type differs.
+    let swiglu = Config { ffn_type: FfnType::SwiGlu, feed_forward_dim: config.feed_forward_dim * 3 / 2, ..config.clone() };
+    checkpoint_error(swiglu, "ffn_type");
+    checkpoint_error(Config { activation: Some(Activation::Relu), ..config.clone() }, "activation");
+    checkpoint_error(Config { causal: false, ..config.clone() }, "causal");
+    checkpoint_error(Config { rope_base: 500000.0, ..config.clone() }, "rope_base");
+    checkpoint_error(Config { attention_window: Some(4), ..config.clone() }, "attention windows");
+    checkpoint_error(Config { position_encoding: PositionEncoding::Alibi, ..config.clone() }, "position_encoding");
+    // Naming the default activation of the feed-forward type is not a mismatch.
+    let explicit = Config { activation: Some(FfnType::Mlp.default_activation()), ..config.clone() };
+    assert!(Model::<f64>::from_checkpoint(&path, &explicit).is_ok());
+
+    // Invalid scaling is reported instead of panicking.
+    let shrink = Config { position_scaling: Some(PositionScaling::Linear { factor: 0.5 }), ..config.clone() };
+    assert!(matches!(Model::<f64>::from_checkpoint(&path, &shrink), Err(Error::InvalidConfig(_))));
+
+    let ntk = Some(PositionScaling::Ntk { factor: 2.0 });
+    let mut model: Model<f64> = Model::load_checkpoint(&path).unwrap();
+    assert!(model.set_position_scaling(ntk).is_ok());
+    let nan = Some(PositionScaling::Ntk { factor: f64::NAN });
+    assert!(matches!(model.set_position_scaling(nan), Err(Error::InvalidConfig(_))));
+    let mut alibi: Model<f64> = Model::new(&Config { position_encoding: PositionEncoding::Alibi, ..config });
+    assert!(matches!(alibi.set_position_scaling(ntk), Err(Error::InvalidConfig(_))));
+}
+
+#[test]
+fn test_from_checkpoint_fine_tune_is_reproducible() {
+    let dir = std::env::temp_dir().join("llm_training_rust_model_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("fine_tune.bin").to_str().unwrap().to_string();
+    let vocab_file = dir.join("fine_tune_vocab.txt");
+    std::fs::write(&vocab_file, "to\nbe\nor\nnot").unwrap();
+    let text_file = dir.join("fine_tune_text.txt");
+    std::fs::write(&text_file, "to be or not to be ".repeat(12)).unwrap();
+    let tokenizer = Tokenizer::new(vocab_file.to_str().unwrap()).unwrap();
+
+    let config = Config {
+        vocab_size: tokenizer.vocab_size(),
+        max_seq_len: 4,
+        embedding_dim: 8,
+        num_heads: 2,
+        feed_forward_dim: 16,
+        dropout_rate: 0.3,
+        seed: 11,
+        ..Default::default()
+    };
+    let model: Model<f64> = Model::new(&config);
+    model.save_checkpoint(&path).unwrap();
+
+    // Shuffling and dropout both draw from the random stream, which is left in a different state
+    // before each run.
+    let fine_tune = |stray_seed: u64| {
+        rng::manual_seed(stray_seed);
+        let mut model: Model<f64> = Model::from_checkpoint(&path, &config).unwrap();
+        let mut data_loader = DataLoader::new(text_file.to_str().unwrap(), 2, 4, &tokenizer).unwrap();
+        data_loader.shuffle = true;
+        let mut optimizer = AdamOptimizer::new(0.01);
+        model.train();
+        let mut losses = Vec::new();
+        for (input, target) in data_loader.iter().take(5) {
+            model.zero_grad();
+            let loss = model.forward(&input, Some(&target), None).unwrap().1.unwrap();
+            loss.backward();
+            optimizer.step(&mut model);
+            losses.push(loss.value().item());
+        }
+        losses
+    };
+    assert_eq!(fine_tune(1), fine_tune(2));
+}
+
+#[test]
+fn test_fit_ignores_the_loader_pad_id() {
+    let dir = std::env::temp_dir().join("llm_training_rust_model_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let vocab_file = dir.join("fit_vocab.txt");
+    std::fs::write(&vocab_file, "to\nbe\nor\nnot").unwrap();
+    let text_file = dir.join("fit_text.txt");
+    std::fs::write(&text_file, "to be or not to be ".repeat(4)).unwrap();
+    let tokenizer = Tokenizer::new(vocab_file.to_str().unwrap()).unwrap();
+    let mut data_loader = DataLoader::new(text_file.to_str().unwrap(), 2, 4, &tokenizer).unwrap();
+    assert_eq!(data_loader.pad_id(), tokenizer.pad_id);
+
+    let config = Config {
+        vocab_size: tokenizer.vocab_size(),
+        max_seq_len: 4,
+        num_epochs: 1,
+        checkpoint_interval: 100,
+        ..Default::default()
+    };
+    let mut model: Model = Model::new(&config);
+    assert_eq!(model.ignore_index, None);
+    model.fit(&mut data_loader, &config).unwrap();
+    assert_eq!(model.ignore_index, Some(tokenizer.pad_id));
+}
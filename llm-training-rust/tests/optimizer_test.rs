@@ -1,6 +1,7 @@
 use llm_training_rust::optimizer::AdamOptimizer;
 use llm_training_rust::model::Model;
 use llm_training_rust::config::Config;
+use llm_training_rust::module::Module;
 
 #[test]
 fn test_optimizer_step() {
@@ -16,20 +17,27 @@ fn test_optimizer_step() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut model = Model::new(&config);
+    let mut model: Model = Model::new(&config);
     let mut optimizer = AdamOptimizer::new(config.learning_rate);
 
     // Perform a forward pass and backward pass to compute gradients
     let input = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
     let target = vec![vec![2, 3, 4, 5], vec![6, 7, 8, 9]];
-    let (logits, _) = model.forward(&input, Some(&target));
-    model.backward(&logits, &input, &target);
+    let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+    loss.unwrap().backward();
+
+    let before = model.parameters().iter().map(|p| p.value().clone()).collect::<Vec<_>>();
 
     // Perform an optimizer step
     optimizer.step(&mut model);
 
-    // Check if the model parameters are updated
-    // You can add more specific assertions based on your implementation
+    // Every parameter that received a gradient moves
+    for (param, old) in model.parameters().iter().zip(&before) {
+        if param.grad().is_some_and(|g| g.to_vec().iter().any(|&x| x != 0.0)) {
+            assert_ne!(&*param.value(), old, "{} was not updated", param.name());
+        }
+    }
 }
\ No newline at end of file
@@ -1,5 +1,9 @@
-use llm_training_rust::positional_encoding::PositionalEncoding;
+use llm_training_rust::positional_encoding::{
+    frequency, LearnedPositionalEmbedding, PositionEncoding, PositionScaling, PositionTable, PositionalEncoding,
+};
 use llm_training_rust::config::Config;
+use llm_training_rust::module::Module;
+use approx::assert_abs_diff_eq;
 
 #[test]
 fn test_positional_encoding_forward() {
@@ -15,13 +19,121 @@ fn test_positional_encoding_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let positional_encoding = PositionalEncoding::new(config.max_seq_len, config.embedding_dim);
+    let positional_encoding: PositionalEncoding = PositionalEncoding::new(config.max_seq_len, config.embedding_dim);
 
     let seq_len = 10;
-    let output = positional_encoding.forward(seq_len);
+    let output = positional_encoding.forward(seq_len).unwrap();
 
-    assert_eq!(output.len(), seq_len);
-    assert_eq!(output[0].len(), config.embedding_dim);
-}
\ No newline at end of file
+    assert_eq!(output.shape(), &[seq_len, config.embedding_dim]);
+}
+
+#[test]
+fn test_sinusoidal_table_interleaves_sin_and_cos() {
+    let positional_encoding: PositionalEncoding<f64> = PositionalEncoding::new(5, 6);
+    assert_eq!(positional_encoding.encodings.shape(), [5, 6]);
+
+    // Dimensions 2i and 2i + 1 share the frequency 10000^(-2i / embedding_dim).
+    assert_eq!(positional_encoding.forward(1).unwrap().to_vec(), [0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+    let row = positional_encoding.forward_at(3, 1).unwrap().to_vec();
+    for i in 0..3 {
+        let angle = 3.0 / 10000f64.powf(2.0 * i as f64 / 6.0);
+        assert_abs_diff_eq!(row[2 * i], angle.sin(), epsilon = 1e-12);
+        assert_abs_diff_eq!(row[2 * i + 1], angle.cos(), epsilon = 1e-12);
+    }
+}
+
+#[test]
+fn test_learned_positional_embedding_trains() {
+    let learned: LearnedPositionalEmbedding<f64> = LearnedPositionalEmbedding::new(6, 4);
+    assert_eq!(learned.max_seq_len(), 6);
+
+    let rows = learned.forward_at(2, 3).unwrap();
+    assert_eq!(rows.shape(), [3, 4]);
+    rows.sum().backward();
+
+    // Only the rows that were read receive a gradient.
+    let grad = learned.parameters()[0].grad().unwrap().to_vec();
+    for (pos, row) in grad.chunks(4).enumerate() {
+        let expected = if (2..5).contains(&pos) { 1.0 } else { 0.0 };
+        assert!(row.iter().all(|&g| g == expected), "position {}: {:?}", pos, row);
+    }
+
+    assert!(learned.forward_at(4, 3).is_err());
+}
+
+#[test]
+fn test_position_table_for_config() {
+    let table = |position_encoding| PositionTable::<f32>::for_config(&Config { position_encoding, ..Default::default() });
+    assert!(matches!(table(PositionEncoding::Sinusoidal), Some(PositionTable::Sinusoidal(_))));
+    assert!(matches!(table(PositionEncoding::Learned), Some(PositionTable::Learned(_))));
+    assert!(table(PositionEncoding::Rope).is_none());
+    assert!(table(PositionEncoding::Alibi).is_none());
+
+    let config: Config = serde_json::from_str(&Config::default().to_json().replace("\"sinusoidal\"", "\"learned\"")).unwrap();
+    assert_eq!(config.position_encoding, PositionEncoding::Learned);
+}
+
+#[test]
+fn test_position_scaling_frequencies() {
+    let unscaled = |pair| frequency(10000.0, 8, pair, None);
+    let scaled = |pair, scaling| frequency(10000.0, 8, pair, Some(scaling));
+
+    // Position interpolation slows every pair down by the factor.
+    let linear = PositionScaling::Linear { factor: 4.0 };
+    for pair in 0..4 {
+        assert_abs_diff_eq!(scaled(pair, linear), unscaled(pair) / 4.0, epsilon = 1e-15);
+    }
+
+    // NTK leaves the fastest pair alone and interpolates the slowest one.
+    let ntk = PositionScaling::Ntk { factor: 4.0 };
+    assert_abs_diff_eq!(scaled(0, ntk), unscaled(0), epsilon = 1e-15);
+    assert_abs_diff_eq!(scaled(3, ntk), unscaled(3) / 4.0, epsilon = 1e-15);
+
+    // YaRN keeps pairs that turn many times within the original context and interpolates the
+    // ones that turn less than once.
+    let yarn = PositionScaling::Yarn { factor: 4.0, original_max_seq_len: 512 };
+    assert_eq!(scaled(0, yarn), unscaled(0));
+    assert_abs_diff_eq!(scaled(3, yarn), unscaled(3) / 4.0, epsilon = 1e-15);
+    assert!(unscaled(1) / 4.0 < scaled(1, yarn) && scaled(1, yarn) < unscaled(1));
+    assert_abs_diff_eq!(yarn.attention_factor(), 0.1 * 4f64.ln() + 1.0);
+    assert_eq!(linear.attention_factor(), 1.0);
+}
+
+#[test]
+fn test_linear_scaling_stretches_sinusoidal_table() {
+    let table: PositionalEncoding<f64> = PositionalEncoding::new(8, 6);
+    let stretched: PositionalEncoding<f64> = PositionalEncoding::with_scaling(32, 6, Some(PositionScaling::Linear { factor: 4.0 }));
+
+    for pos in 0..8 {
+        let (a, b) = (table.forward_at(pos, 1).unwrap(), stretched.forward_at(4 * pos, 1).unwrap());
+        for (x, y) in a.to_vec().iter().zip(b.to_vec()) {
+            assert_abs_diff_eq!(*x, y, epsilon = 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_learned_table_interpolates() {
+    let mut learned: LearnedPositionalEmbedding<f64> = LearnedPositionalEmbedding::new(4, 3);
+    let table = learned.forward_at(0, 4).unwrap().value().clone();
+    learned.interpolate(8);
+    assert_eq!(learned.max_seq_len(), 8);
+
+    // Even rows are the trained ones, odd rows lie halfway between neighbours, and the last row
+    // repeats the last trained one.
+    let grown = learned.forward_at(0, 8).unwrap().value().clone();
+    for pos in 0..4 {
+        assert_eq!(grown.narrow(0, 2 * pos, 1), table.narrow(0, pos, 1));
+    }
+    let halfway = table.narrow(0, 1, 1).add(&table.narrow(0, 2, 1)).scale(0.5);
+    for (x, y) in grown.narrow(0, 3, 1).to_vec().iter().zip(halfway.to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 1e-15);
+    }
+    assert_eq!(grown.narrow(0, 7, 1), table.narrow(0, 3, 1));
+
+    learned.interpolate(2);
+    assert_eq!(learned.max_seq_len(), 8);
+}
@@ -0,0 +1,81 @@
+use llm_training_rust::config::Config;
+use llm_training_rust::data_loader::DataLoader;
+use llm_training_rust::generation::generate;
+use llm_training_rust::model::Model;
+use llm_training_rust::module::Module;
+use llm_training_rust::optimizer::AdamOptimizer;
+use llm_training_rust::tokenizer::Tokenizer;
+
+mod common;
+
+use common::write_fixture;
+
+fn fixtures() -> (Tokenizer, String) {
+    let words = ["to", "be", "or", "not", "that", "is", "the", "question"];
+    let vocab_file = write_fixture("vocab.txt", &words.join("\n"));
+    let text = (0..200).map(|i| words[(i * 7 + i / 3) % words.len()]).collect::<Vec<_>>().join(" ");
+    let data_file = write_fixture("train.txt", &text);
+    (Tokenizer::new(&vocab_file).unwrap(), data_file)
+}
+
+fn config(seed: u64) -> Config {
+    Config {
+        vocab_size: 10,
+        max_seq_len: 8,
+        embedding_dim: 16,
+        num_layers: 1,
+        feed_forward_dim: 32,
+        dropout_rate: 0.2,
+        learning_rate: 0.01,
+        seed,
+        ..Default::default()
+    }
+}
+
+/// Trains for a few shuffled batches with dropout on, then samples a continuation.
+fn run(seed: u64) -> (Vec<u32>, String) {
+    let (tokenizer, data_file) = fixtures();
+    let config = config(seed);
+    let mut model: Model = Model::new(&config);
+    let mut optimizer = AdamOptimizer::new(config.learning_rate);
+    let mut data_loader = DataLoader::new(&data_file, config.batch_size, config.max_seq_len, &tokenizer).unwrap();
+    data_loader.shuffle = true;
+
+    model.train();
+    let mut losses = Vec::new();
+    for (input, target) in data_loader.iter().take(5) {
+        model.zero_grad();
+        let (_, loss) = model.forward(&input, Some(&target), None).unwrap();
+        let loss = loss.unwrap();
+        loss.backward();
+        optimizer.step(&mut model);
+        losses.push(loss.value().item().to_bits());
+    }
+
+    let text = generate(&mut model, "to be", &tokenizer, 6).unwrap();
+    (losses, text)
+}
+
+#[test]
+fn test_same_seed_gives_identical_weights() {
+    let a: Model = Model::new(&config(7));
+    let b: Model = Model::new(&config(7));
+    let c: Model = Model::new(&config(8));
+
+    for ((pa, pb), pc) in a.parameters().iter().zip(b.parameters()).zip(c.parameters()) {
+        assert_eq!(*pa.value(), *pb.value(), "{} differs for the same seed", pa.name());
+        if pa.name() == "embedding_matrix" {
+            assert_ne!(*pa.value(), *pc.value());
+        }
+    }
+}
+
+#[test]
+fn test_same_seed_gives_identical_losses_and_text() {
+    let (losses_a, text_a) = run(3);
+    let (losses_b, text_b) = run(3);
+
+    assert_eq!(losses_a, losses_b);
+    assert_eq!(text_a, text_b);
+    assert_ne!(losses_a, run(4).0);
+}
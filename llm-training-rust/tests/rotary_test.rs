@@ -0,0 +1,109 @@
+use llm_training_rust::autograd::Var;
+use llm_training_rust::config::Config;
+use llm_training_rust::init::Init;
+use llm_training_rust::model::Model;
+use llm_training_rust::positional_encoding::{PositionEncoding, PositionScaling};
+use llm_training_rust::rotary::RotaryEmbedding;
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+fn dot(a: &Tensor<f64>, b: &Tensor<f64>) -> f64 {
+    a.mul(b).sum()
+}
+
+#[test]
+fn test_rotary_preserves_norm_and_offsets() {
+    let rotary = RotaryEmbedding::new(8, 10000.0);
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[2, 5, 8], 1, 1);
+    let rotated = rotary.apply(&Var::constant(x.clone()), 3).value().clone();
+
+    for pos in 0..5 {
+        let before = x.narrow(1, pos, 1);
+        let after = rotated.narrow(1, pos, 1);
+        assert_abs_diff_eq!(dot(&before, &before), dot(&after, &after), epsilon = 1e-10);
+    }
+
+    // Position 0 is the identity, and an offset rotates like the same rows further along.
+    assert_eq!(rotary.apply(&Var::constant(x.narrow(1, 0, 1)), 0).value().clone(), x.narrow(1, 0, 1));
+    let shifted = rotary.apply(&Var::constant(x.narrow(1, 2, 3)), 5).value().clone();
+    for (a, b) in shifted.to_vec().iter().zip(rotated.narrow(1, 2, 3).to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+    }
+}
+
+#[test]
+fn test_rotary_scores_depend_only_on_distance() {
+    let rotary = RotaryEmbedding::new(8, 10000.0);
+    let q: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 8], 1, 1);
+    let k: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 8], 1, 1);
+    let score = |q_pos: usize, k_pos: usize| {
+        let q = rotary.apply(&Var::constant(q.clone()), q_pos).value().clone();
+        let k = rotary.apply(&Var::constant(k.clone()), k_pos).value().clone();
+        dot(&q, &k)
+    };
+
+    assert_abs_diff_eq!(score(5, 2), score(13, 10), epsilon = 1e-10);
+    assert_abs_diff_eq!(score(0, 0), score(7, 7), epsilon = 1e-10);
+    assert!((score(5, 2) - score(5, 4)).abs() > 1e-6);
+}
+
+#[test]
+fn test_rope_model_adds_no_positional_encoding() {
+    let config = Config {
+        position_encoding: PositionEncoding::Rope,
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    assert!(config.validate().is_ok());
+    let mut model: Model<f64> = Model::new(&config);
+
+    // The same token at two positions gets the same embedding, so only attention can tell them
+    // apart: the first position's logits depend on nothing but the token itself.
+    let (a, _) = model.forward(&[vec![5, 5]], None, None).unwrap();
+    let (b, _) = model.forward(&[vec![5, 9]], None, None).unwrap();
+    assert_eq!(a.value().narrow(1, 0, 1), b.value().narrow(1, 0, 1));
+
+    let input = vec![vec![1, 2, 3, 4, 5]];
+    let (full, _) = model.forward(&input, None, None).unwrap();
+    model.forward_step(&[vec![1, 2, 3]]).unwrap();
+    model.forward_step(&[vec![4]]).unwrap();
+    let last = model.forward_step(&[vec![5]]).unwrap();
+    for (x, y) in last.value().to_vec().iter().zip(full.value().narrow(1, 4, 1).to_vec()) {
+        assert_abs_diff_eq!(*x, y, epsilon = 1e-10);
+    }
+}
+
+#[test]
+fn test_rope_config() {
+    let json = Config::default().to_json().replace("\"sinusoidal\"", "\"rope\"");
+    let config = Config::from_json(&json).unwrap();
+    assert_eq!(config.position_encoding, PositionEncoding::Rope);
+    assert_eq!(config.rope_base, 10000.0);
+
+    let odd_heads = Config {
+        embedding_dim: 12,
+        num_heads: 4,
+        ..config.clone()
+    };
+    assert!(odd_heads.validate().is_err());
+    assert!(Config { rope_base: 1.0, ..config }.validate().is_err());
+}
+
+#[test]
+fn test_rotary_linear_scaling_stretches_positions() {
+    let mut rotary = RotaryEmbedding::new(8, 10000.0);
+    let x: Tensor<f64> = Init::Normal(1.0).tensor(&[1, 1, 8], 1, 1);
+    let at = |rotary: &RotaryEmbedding, pos| rotary.apply(&Var::constant(x.clone()), pos).value().clone();
+    let trained = at(&rotary, 3);
+
+    rotary.set_scaling(Some(PositionScaling::Linear { factor: 4.0 }));
+    for (a, b) in at(&rotary, 12).to_vec().iter().zip(trained.to_vec()) {
+        assert_abs_diff_eq!(*a, b, epsilon = 1e-12);
+    }
+
+    // YaRN also scales the rotated vectors by its attention factor.
+    let yarn = PositionScaling::Yarn { factor: 4.0, original_max_seq_len: 16 };
+    rotary.set_scaling(Some(yarn));
+    let norm = |t: &Tensor<f64>| dot(t, t).sqrt();
+    assert_abs_diff_eq!(norm(&at(&rotary, 5)), yarn.attention_factor() * norm(&x), epsilon = 1e-12);
+}
@@ -0,0 +1,22 @@
+use llm_training_rust::silu::{silu, silu_backward};
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_silu_known_values() {
+    // SiLU(x) = x * sigmoid(x)
+    assert_abs_diff_eq!(silu(0.0), 0.0);
+    assert_abs_diff_eq!(silu(1.0), 0.7311, epsilon = 1e-4);
+    assert_abs_diff_eq!(silu(-1.0), -0.2689, epsilon = 1e-4);
+    assert_abs_diff_eq!(silu(-40.0), 0.0, epsilon = 1e-12);
+}
+
+#[test]
+fn test_silu_backward() {
+    // Matches a central difference of the forward pass.
+    let eps = 1e-6;
+    for &x in &[-3.0, -1.0, -0.1, 0.0, 0.5, 1.0, 2.0, 3.0] {
+        let numerical = (silu(x + eps) - silu(x - eps)) / (2.0 * eps);
+        assert_abs_diff_eq!(silu_backward(1.0, x), numerical, epsilon = 1e-8);
+    }
+    assert_abs_diff_eq!(silu_backward(2.0, 0.0), 1.0);
+}
@@ -0,0 +1,73 @@
+use llm_training_rust::tensor::Tensor;
+use approx::assert_abs_diff_eq;
+
+#[test]
+fn test_tensor_reshape_and_transpose() {
+    let t = Tensor::new((0..6).map(|x| x as f64).collect(), &[2, 3]);
+    let r = t.reshape(&[3, 2]);
+    assert_eq!(r.shape(), &[3, 2]);
+    assert_eq!(r.to_vec(), t.to_vec());
+
+    let tt = t.transpose(0, 1);
+    assert_eq!(tt.shape(), &[3, 2]);
+    assert!(!tt.is_contiguous());
+    assert_eq!(tt.to_vec(), vec![0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+    assert_eq!(tt.get(&[2, 1]), t.get(&[1, 2]));
+}
+
+#[test]
+fn test_tensor_broadcasting_add() {
+    let a = Tensor::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+    let b = Tensor::new(vec![10.0, 20.0, 30.0], &[3]);
+    let c = a.add(&b);
+    assert_eq!(c.shape(), &[2, 3]);
+    assert_eq!(c.to_vec(), vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+
+    let col = Tensor::new(vec![1.0, 2.0], &[2, 1]);
+    assert_eq!(a.mul(&col).to_vec(), vec![1.0, 2.0, 3.0, 8.0, 10.0, 12.0]);
+    assert_eq!(c.sum_to(&[3]).to_vec(), vec![25.0, 47.0, 69.0]);
+}
+
+#[test]
+fn test_tensor_batched_matmul() {
+    let a = Tensor::new((0..12).map(|x| x as f64).collect(), &[2, 2, 3]);
+    let b = Tensor::new((0..6).map(|x| x as f64).collect(), &[3, 2]);
+    let c = a.matmul(&b);
+    assert_eq!(c.shape(), &[2, 2, 2]);
+    assert_eq!(c.to_vec(), vec![10.0, 13.0, 28.0, 40.0, 46.0, 67.0, 64.0, 94.0]);
+
+    let batched_b = Tensor::cat(&[&b.reshape(&[1, 3, 2]), &b.reshape(&[1, 3, 2])], 0);
+    assert_eq!(a.matmul(&batched_b), c);
+
+    let at = a.transpose(1, 2);
+    let gram = a.matmul(&at);
+    assert_eq!(gram.shape(), &[2, 2, 2]);
+    assert_eq!(gram.get(&[0, 0, 1]), gram.get(&[0, 1, 0]));
+}
+
+#[test]
+fn test_tensor_softmax_along_axis() {
+    let t = Tensor::new(vec![1.0, 2.0, 3.0, 1.0, 1.0, 1.0], &[2, 3]);
+    let rows = t.softmax(1);
+    for r in 0..2 {
+        let sum: f64 = (0..3).map(|c| rows.get(&[r, c])).sum();
+        assert_abs_diff_eq!(sum, 1.0, epsilon = 1e-12);
+    }
+    assert_abs_diff_eq!(rows.get(&[1, 0]), 1.0 / 3.0, epsilon = 1e-12);
+
+    let cols = t.softmax(0);
+    for c in 0..3 {
+        assert_abs_diff_eq!(cols.get(&[0, c]) + cols.get(&[1, c]), 1.0, epsilon = 1e-12);
+    }
+}
+
+#[test]
+fn test_tensor_narrow_and_cat() {
+    let t = Tensor::new((0..12).map(|x| x as f64).collect(), &[2, 3, 2]);
+    let n = t.narrow(1, 1, 2);
+    assert_eq!(n.shape(), &[2, 2, 2]);
+    assert_eq!(n.to_vec(), vec![2.0, 3.0, 4.0, 5.0, 8.0, 9.0, 10.0, 11.0]);
+
+    let joined = Tensor::cat(&[&t.narrow(1, 0, 1), &n], 1);
+    assert_eq!(joined, t);
+}
@@ -1,24 +1,33 @@
 use llm_training_rust::tokenizer::Tokenizer;
 
+fn write_vocab(name: &str) -> String {
+    let dir = std::env::temp_dir().join("llm_training_rust_tokenizer_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, "This\nis\na\nsample\ntext.\n").unwrap();
+    path.to_str().unwrap().to_string()
+}
+
 #[test]
 fn test_tokenizer_encode() {
-    let vocab_file = "path/to/vocab.txt";
-    let tokenizer = Tokenizer::new(vocab_file);
+    let vocab_file = write_vocab("encode_vocab.txt");
+    let tokenizer = Tokenizer::new(&vocab_file).unwrap();
 
     let text = "This is a sample text.";
     let encoded = tokenizer.encode(text);
 
     assert!(!encoded.is_empty());
+    assert_eq!(encoded, vec![0, 1, 2, 3, 4]);
 }
 
 #[test]
 fn test_tokenizer_decode() {
-    let vocab_file = "path/to/vocab.txt";
-    let tokenizer = Tokenizer::new(vocab_file);
+    let vocab_file = write_vocab("decode_vocab.txt");
+    let tokenizer = Tokenizer::new(&vocab_file).unwrap();
 
     let text = "This is a sample text.";
     let encoded = tokenizer.encode(text);
     let decoded = tokenizer.decode(&encoded);
 
     assert_eq!(decoded, text);
-}
\ No newline at end of file
+}
@@ -1,5 +1,9 @@
 use llm_training_rust::transformer::{TransformerLayer, Transformer};
 use llm_training_rust::config::Config;
+use llm_training_rust::autograd::Var;
+use llm_training_rust::init::Init;
+use llm_training_rust::module::Module;
+use llm_training_rust::tensor::Tensor;
 
 #[test]
 fn test_transformer_layer_forward() {
@@ -15,15 +19,15 @@ fn test_transformer_layer_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut transformer_layer = TransformerLayer::new(&config);
+    let mut transformer_layer: TransformerLayer = TransformerLayer::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = transformer_layer.forward(&input);
+    let input = Var::constant(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = transformer_layer.forward(&input, None).unwrap();
 
-    assert_eq!(output.len(), config.max_seq_len);
-    assert_eq!(output[0].len(), config.embedding_dim);
+    assert_eq!(output.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
 }
 
 #[test]
@@ -40,18 +44,19 @@ fn test_transformer_layer_backward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut transformer_layer = TransformerLayer::new(&config);
+    let mut transformer_layer: TransformerLayer = TransformerLayer::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = transformer_layer.forward(&input);
+    let input = Var::leaf(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = transformer_layer.forward(&input, None).unwrap();
 
-    let grad_output = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let grad_input = transformer_layer.backward(&grad_output);
+    let grad_output = Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]);
+    output.backward_with(grad_output);
+    let grad_input = input.grad().unwrap();
 
-    assert_eq!(grad_input.len(), config.max_seq_len);
-    assert_eq!(grad_input[0].len(), config.embedding_dim);
+    assert_eq!(grad_input.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
 }
 
 #[test]
@@ -68,15 +73,15 @@ fn test_transformer_forward() {
         batch_size: 2,
         num_epochs: 1,
         checkpoint_interval: 10,
+        ..Default::default()
     };
 
-    let mut transformer = Transformer::new(&config);
+    let mut transformer: Transformer = Transformer::new(&config);
 
-    let input = vec![vec![1.0; config.embedding_dim]; config.max_seq_len];
-    let output = transformer.forward(&input);
+    let input = Var::constant(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = transformer.forward(&input, None).unwrap();
 
-    assert_eq!(output.len(), config.max_seq_len);
-    assert_eq!(output[0].len(), config.embedding_dim);
+    assert_eq!(output.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
 }
 
 #[test]
@@ -91,4 +96,62 @@ fn test_transformer_backward() {
         dropout_rate: 0.1,
         learning_rate: 0.001,
         batch_size: 2,
-        num_
\ No newline at end of file
+        num_epochs: 1,
+        checkpoint_interval: 10,
+        ..Default::default()
+    };
+
+    let mut transformer: Transformer = Transformer::new(&config);
+
+    let input = Var::leaf(Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]));
+    let output = transformer.forward(&input, None).unwrap();
+
+    let grad_output = Tensor::ones(&[config.batch_size, config.max_seq_len, config.embedding_dim]);
+    output.backward_with(grad_output);
+    let grad_input = input.grad().unwrap();
+
+    assert_eq!(grad_input.shape(), [config.batch_size, config.max_seq_len, config.embedding_dim]);
+}
+
+#[test]
+fn test_transformer_alternates_local_and_global_layers() {
+    let config = Config {
+        num_layers: 4,
+        attention_window: Some(8),
+        global_attention_every: Some(2),
+        ..Default::default()
+    };
+    assert!(config.validate().is_ok());
+
+    let transformer: Transformer = Transformer::new(&config);
+    let windows = transformer.layers().iter().map(|layer| layer.attention().window()).collect::<Vec<_>>();
+    assert_eq!(windows, [Some(8), None, Some(8), None]);
+}
+
+#[test]
+fn test_decoder_layers_attend_over_memory() {
+    let config = Config {
+        embedding_dim: 8,
+        num_heads: 2,
+        num_layers: 2,
+        feed_forward_dim: 16,
+        dropout_rate: 0.0,
+        ..Default::default()
+    };
+    let mut decoder: Transformer = Transformer::decoder(&config);
+    assert!(decoder.layers().iter().all(|layer| layer.cross_attention().is_some()));
+    let names = decoder.named_parameters().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    assert!(names.contains(&"layers.1.cross_attention.key_matrix.weight".to_string()));
+    assert!(names.contains(&"layers.1.layer_norm_cross.gamma".to_string()));
+
+    let input = Var::constant(Tensor::ones(&[2, 3, 8]));
+    let memory = Var::leaf(Init::Normal(1.0).tensor(&[2, 4, 8], 1, 1));
+    let output = decoder.forward_with_memory(&input, None, &memory, None).unwrap();
+    assert_eq!(output.shape(), [2, 3, 8]);
+
+    // The gradient reaches the memory, i.e. the encoder in a full model. A plain sum would be
+    // constant after the final layer norm, so weight the outputs.
+    let weights = Var::constant(Init::Normal(1.0).tensor(&[2, 3, 8], 1, 1));
+    output.mul(&weights).sum().backward();
+    assert!(memory.grad().unwrap().to_vec().iter().any(|&g| g != 0.0));
+}